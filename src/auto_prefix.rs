@@ -0,0 +1,95 @@
+//! Infers the tag prefix of a package in a monorepo from the directory nutag runs in,
+//! e.g. `foo` inside `crates/foo`.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use nanoserde::DeJson;
+
+/// Looks at the Cargo and npm package names and the directory name, preferring whichever
+/// already has tags. Without any tags the package name is used, the directory name alone
+/// is too weak a hint.
+pub fn detect(dir: &Path, existing_prefixes: &BTreeSet<String>) -> Option<String> {
+    let read = |file: &str| std::fs::read_to_string(dir.join(file)).ok();
+
+    let package_names: Vec<String> = [
+        read("Cargo.toml").and_then(|raw| cargo_package_name(&raw)),
+        read("package.json").and_then(|raw| npm_package_name(&raw)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let dir_name = dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string);
+
+    package_names
+        .iter()
+        .chain(dir_name.iter())
+        .find(|name| existing_prefixes.contains(*name))
+        .or(package_names.first())
+        .cloned()
+}
+
+/// The `name` in the `[package]` table of a Cargo.toml
+fn cargo_package_name(raw: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in raw.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+            continue;
+        }
+        if !in_package {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if key.trim() == "name" {
+            return Some(value.trim().trim_matches('"').to_string());
+        }
+    }
+    None
+}
+
+/// The `name` of a package.json, without the scope, which can't be part of a prefix
+fn npm_package_name(raw: &str) -> Option<String> {
+    #[derive(DeJson)]
+    struct PackageJson {
+        #[nserde(default)]
+        name: String,
+    }
+
+    let package: PackageJson = DeJson::deserialize_json(raw).ok()?;
+    let name = Some(package.name).filter(|name| !name.is_empty())?;
+    match name.split_once('/') {
+        Some((_scope, name)) => Some(name.to_string()),
+        None => Some(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cargo_package_name, npm_package_name};
+
+    #[test]
+    fn reads_package_names() {
+        let cargo = r#"
+            [workspace]
+            name = "not-this"
+
+            [package]
+            version = "0.1.0"
+            name = "foo"
+        "#;
+        assert_eq!(cargo_package_name(cargo).as_deref(), Some("foo"));
+        assert_eq!(cargo_package_name("[workspace]\nmembers = []"), None);
+
+        assert_eq!(
+            npm_package_name(r#"{"name": "@acme/web", "version": "1.0.0"}"#).as_deref(),
+            Some("web")
+        );
+        assert_eq!(npm_package_name(r#"{"private": true}"#), None);
+    }
+}
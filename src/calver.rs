@@ -0,0 +1,214 @@
+//! Calendar versioning, where the version says when it was released, e.g. `2025.3.1` for
+//! the second release in March 2025. See <https://calver.org>.
+
+use std::str::FromStr;
+
+use anyhow::bail;
+use semver::{BuildMetadata, Prerelease, Version};
+
+use crate::date::Date;
+
+pub const DEFAULT_FORMAT: &str = "YYYY.MM.MICRO";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Segment {
+    /// `YYYY`, or `YY`/`0Y` for the years since 2000
+    Year {
+        short: bool,
+        padded: bool,
+    },
+    Month {
+        padded: bool,
+    },
+    Day {
+        padded: bool,
+    },
+    /// Counts the releases within the same period
+    Micro,
+}
+
+impl Segment {
+    fn today(self, today: Date) -> Option<u64> {
+        match self {
+            Segment::Year { short: false, .. } => Some(today.year as u64),
+            Segment::Year { short: true, .. } => Some(today.year as u64 - 2000),
+            Segment::Month { .. } => Some(u64::from(today.month)),
+            Segment::Day { .. } => Some(u64::from(today.day)),
+            Segment::Micro => None,
+        }
+    }
+
+    fn padded(self) -> bool {
+        match self {
+            Segment::Year { padded, .. } | Segment::Month { padded } | Segment::Day { padded } => {
+                padded
+            }
+            Segment::Micro => false,
+        }
+    }
+}
+
+/// A format like `YYYY.MM.MICRO` or `YY.0M.PATCH`, always three segments so the versions
+/// still fit into semver's major, minor and patch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalVer {
+    segments: [Segment; 3],
+}
+
+impl FromStr for CalVer {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        let segments = format
+            .split('.')
+            .map(|token| {
+                Ok(match token {
+                    "YYYY" => Segment::Year {
+                        short: false,
+                        padded: false,
+                    },
+                    "YY" => Segment::Year {
+                        short: true,
+                        padded: false,
+                    },
+                    "0Y" => Segment::Year {
+                        short: true,
+                        padded: true,
+                    },
+                    "MM" => Segment::Month { padded: false },
+                    "0M" => Segment::Month { padded: true },
+                    "DD" => Segment::Day { padded: false },
+                    "0D" => Segment::Day { padded: true },
+                    "MICRO" | "PATCH" => Segment::Micro,
+                    other => bail!(
+                        "unknown calver segment {other}, expected YYYY, YY, 0Y, MM, 0M, DD, 0D or MICRO"
+                    ),
+                })
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+
+        let Ok(segments) = <[Segment; 3]>::try_from(segments) else {
+            bail!("a calver format has three segments, e.g. {DEFAULT_FORMAT}");
+        };
+        if segments[..2].contains(&Segment::Micro) {
+            bail!("MICRO has to be the last segment of a calver format");
+        }
+        Ok(Self { segments })
+    }
+}
+
+impl CalVer {
+    /// Parses `2025.03.1-rc0`, which isn't valid semver because of the leading zero
+    pub fn parse(&self, raw: &str) -> Option<Version> {
+        let (raw, build) = match raw.split_once('+') {
+            Some((raw, build)) => (raw, BuildMetadata::new(build).ok()?),
+            None => (raw, BuildMetadata::EMPTY),
+        };
+        let (numbers, pre) = match raw.split_once('-') {
+            Some((numbers, pre)) => (numbers, Prerelease::new(pre).ok()?),
+            None => (raw, Prerelease::EMPTY),
+        };
+
+        let numbers: Vec<u64> = numbers
+            .split('.')
+            .map(|n| n.parse().ok())
+            .collect::<Option<_>>()?;
+        let [major, minor, patch] = numbers[..] else {
+            return None;
+        };
+
+        let mut version = Version::new(major, minor, patch);
+        version.pre = pre;
+        version.build = build;
+        Some(version)
+    }
+
+    pub fn render(&self, version: &Version) -> String {
+        let numbers: Vec<String> = self
+            .segments
+            .iter()
+            .zip([version.major, version.minor, version.patch])
+            .map(|(segment, n)| {
+                if segment.padded() {
+                    format!("{n:02}")
+                } else {
+                    n.to_string()
+                }
+            })
+            .collect();
+        let mut out = numbers.join(".");
+        if !version.pre.is_empty() {
+            out = format!("{out}-{}", version.pre);
+        }
+        if !version.build.is_empty() {
+            out = format!("{out}+{}", version.build);
+        }
+        out
+    }
+
+    /// The release after `latest`: today's date, counting up MICRO if `latest` was released
+    /// in the same period. A prerelease of today's version is followed by that version.
+    pub fn next(&self, latest: Option<&Version>, today: Date) -> Result<Version, anyhow::Error> {
+        let mut numbers = self
+            .segments
+            .map(|segment| segment.today(today).unwrap_or(0));
+
+        if let Some(latest) = latest {
+            let previous = [latest.major, latest.minor, latest.patch];
+            let same_period = self
+                .segments
+                .iter()
+                .zip(previous.iter().zip(numbers))
+                .all(|(segment, (before, now))| *segment == Segment::Micro || *before == now);
+
+            if same_period {
+                match self.segments[2] {
+                    Segment::Micro if latest.pre.is_empty() => numbers[2] = previous[2] + 1,
+                    Segment::Micro => numbers[2] = previous[2],
+                    _ if !latest.pre.is_empty() => {}
+                    _ => bail!("{today} already has a release, add MICRO to the calver format to release more than once"),
+                }
+            }
+        }
+
+        Ok(Version::new(numbers[0], numbers[1], numbers[2]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::Version;
+
+    use super::CalVer;
+    use crate::date::Date;
+
+    #[test]
+    fn rolls_over_to_today() {
+        let calver: CalVer = "YY.0M.MICRO".parse().unwrap();
+        let today = Date {
+            year: 2025,
+            month: 3,
+            day: 11,
+        };
+
+        let latest = calver.parse("25.02.4").unwrap();
+        assert_eq!(
+            calver.next(Some(&latest), today).unwrap(),
+            Version::new(25, 3, 0)
+        );
+        let latest = calver.parse("25.03.0").unwrap();
+        let next = calver.next(Some(&latest), today).unwrap();
+        assert_eq!(calver.render(&next), "25.03.1");
+        let latest = calver.parse("25.03.1-pre2").unwrap();
+        assert_eq!(
+            calver.next(Some(&latest), today).unwrap(),
+            Version::new(25, 3, 1)
+        );
+
+        let daily: CalVer = "YYYY.MM.DD".parse().unwrap();
+        assert!(daily.next(Some(&Version::new(2025, 3, 11)), today).is_err());
+
+        assert!("YYYY.MICRO.MM".parse::<CalVer>().is_err());
+        assert!("YYYY.MM".parse::<CalVer>().is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! Turns the commits since the last tag into a markdown changelog section, grouped by
+//! their [conventional commit](https://www.conventionalcommits.org) type.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use regex_lite::Regex;
+
+use crate::{BumpKind, Commit};
+
+pub const FILE_NAME: &str = "CHANGELOG.md";
+
+/// The interesting parts of a conventional commit message
+#[derive(Debug, PartialEq, Eq)]
+pub struct Conventional<'a> {
+    pub kind: &'a str,
+    pub scope: Option<&'a str>,
+    pub breaking: bool,
+    pub description: &'a str,
+}
+
+impl<'a> Conventional<'a> {
+    /// Parses `type(scope)!: description`, with breaking changes also being signalled
+    /// by a `BREAKING CHANGE:` footer. Returns `None` for commits that don't follow the convention.
+    pub fn parse(message: &'a str) -> Option<Self> {
+        let subject = message.lines().next()?;
+        let re = Regex::new(r"^([a-zA-Z]+)(?:\(([^)]*)\))?(!)?: (.+)$").unwrap();
+        let caps = re.captures(subject)?;
+
+        let footer_breaking = message.lines().skip(1).any(|line| {
+            line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+        });
+
+        Some(Self {
+            kind: caps.get(1)?.as_str(),
+            scope: caps.get(2).map(|m| m.as_str()),
+            breaking: caps.get(3).is_some() || footer_breaking,
+            description: caps.get(4)?.as_str(),
+        })
+    }
+}
+
+/// The sections of the changelog, in the order they are rendered
+const SECTIONS: &[(&str, &str)] = &[
+    ("feat", "Features"),
+    ("fix", "Bug Fixes"),
+    ("perf", "Performance Improvements"),
+    ("revert", "Reverts"),
+    ("refactor", "Refactoring"),
+    ("docs", "Documentation"),
+];
+
+/// Renders a changelog section titled `title` for the given commits
+pub fn render(title: &str, date: &str, commits: &[Commit]) -> String {
+    let mut breaking = Vec::new();
+    let mut grouped: Vec<Vec<String>> = vec![Vec::new(); SECTIONS.len()];
+    let mut other = Vec::new();
+
+    for commit in commits {
+        let Some(conventional) = Conventional::parse(&commit.message) else {
+            other.push(format!("- {} ({})", commit.subject(), commit.short_hash));
+            continue;
+        };
+
+        let entry = match conventional.scope {
+            Some(scope) => format!(
+                "- **{scope}:** {} ({})",
+                conventional.description, commit.short_hash
+            ),
+            None => format!("- {} ({})", conventional.description, commit.short_hash),
+        };
+
+        if conventional.breaking {
+            breaking.push(entry.clone());
+        }
+        match SECTIONS
+            .iter()
+            .position(|(kind, _)| kind.eq_ignore_ascii_case(conventional.kind))
+        {
+            Some(idx) => grouped[idx].push(entry),
+            None => other.push(entry),
+        }
+    }
+
+    let mut out = format!("## {title} ({date})\n");
+    let mut section = |heading: &str, entries: &[String]| {
+        if entries.is_empty() {
+            return;
+        }
+        let _ = write!(out, "\n### {heading}\n\n");
+        for entry in entries {
+            let _ = writeln!(out, "{entry}");
+        }
+    };
+
+    section("⚠ Breaking Changes", &breaking);
+    for ((_, heading), entries) in SECTIONS.iter().zip(&grouped) {
+        section(heading, entries);
+    }
+    section("Other Changes", &other);
+
+    out
+}
+
+/// Breaking changes call for a major bump, features for a minor one and everything else for a patch
+pub fn suggested_bump(commits: &[Commit]) -> BumpKind {
+    commits
+        .iter()
+        .filter_map(|commit| Conventional::parse(&commit.message))
+        .map(|conventional| {
+            if conventional.breaking {
+                BumpKind::Major
+            } else if conventional.kind.eq_ignore_ascii_case("feat") {
+                BumpKind::Minor
+            } else {
+                BumpKind::Patch
+            }
+        })
+        .max()
+        .unwrap_or(BumpKind::Patch)
+}
+
+/// Adds `section` to the changelog file, newest entries first
+pub fn write(path: &Path, section: &str) -> Result<(), anyhow::Error> {
+    let existing = if path.exists() {
+        std::fs::read_to_string(path)?
+    } else {
+        "# Changelog\n".to_string()
+    };
+
+    std::fs::write(path, insert(&existing, section))?;
+    Ok(())
+}
+
+/// Puts `section` above the first existing release section, keeping any preamble at the top
+fn insert(existing: &str, section: &str) -> String {
+    if existing.starts_with("## ") {
+        return format!("{section}\n{existing}");
+    }
+    match existing.find("\n## ") {
+        Some(idx) => format!(
+            "{}{section}\n{}",
+            &existing[..idx + 1],
+            &existing[idx + 1..]
+        ),
+        None => format!("{}\n\n{section}", existing.trim_end()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{insert, render, suggested_bump, Conventional};
+    use crate::{BumpKind, Commit};
+
+    fn commit(hash: &str, message: &str) -> Commit {
+        Commit {
+            short_hash: hash.to_string(),
+            author: "someone".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_conventional_commits() {
+        assert_eq!(
+            Conventional::parse("feat(api)!: drop v1 endpoints"),
+            Some(Conventional {
+                kind: "feat",
+                scope: Some("api"),
+                breaking: true,
+                description: "drop v1 endpoints",
+            })
+        );
+        assert!(
+            Conventional::parse("fix: something\n\nBREAKING CHANGE: it is different now")
+                .unwrap()
+                .breaking
+        );
+        assert_eq!(Conventional::parse("Merge branch 'main'"), None);
+    }
+
+    #[test]
+    fn groups_commits_by_type() {
+        let commits = [
+            commit("aaa", "feat(cli): add list command"),
+            commit("bbb", "fix: handle empty repos"),
+            commit("ccc", "update readme"),
+            commit("ddd", "feat!: new config format"),
+        ];
+
+        assert_eq!(
+            render("v1.0.0", "2025-03-11", &commits),
+            "## v1.0.0 (2025-03-11)
+
+### ⚠ Breaking Changes
+
+- new config format (ddd)
+
+### Features
+
+- **cli:** add list command (aaa)
+- new config format (ddd)
+
+### Bug Fixes
+
+- handle empty repos (bbb)
+
+### Other Changes
+
+- update readme (ccc)
+"
+        );
+    }
+
+    #[test]
+    fn suggests_a_bump_based_on_the_commits() {
+        let fix = commit("aaa", "fix: handle empty repos");
+        let feat = commit("bbb", "feat(cli): add list command");
+        let breaking = commit(
+            "ccc",
+            "refactor: rename flags\n\nBREAKING CHANGE: --foo is --bar",
+        );
+
+        assert_eq!(suggested_bump(&[]), BumpKind::Patch);
+        assert_eq!(suggested_bump(std::slice::from_ref(&fix)), BumpKind::Patch);
+        assert_eq!(
+            suggested_bump(&[fix.clone(), feat.clone()]),
+            BumpKind::Minor
+        );
+        assert_eq!(suggested_bump(&[feat, breaking, fix]), BumpKind::Major);
+    }
+
+    #[test]
+    fn inserts_new_sections_above_older_ones() {
+        let existing = "# Changelog\n\n## v0.1.0 (2025-01-01)\n\n- first\n";
+        let section = "## v0.2.0 (2025-02-01)\n\n- second\n";
+
+        assert_eq!(
+            insert(existing, section),
+            "# Changelog\n\n## v0.2.0 (2025-02-01)\n\n- second\n\n## v0.1.0 (2025-01-01)\n\n- first\n"
+        );
+        assert_eq!(
+            insert("# Changelog\n", section),
+            "# Changelog\n\n## v0.2.0 (2025-02-01)\n\n- second\n"
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! The per-project configuration that lives in `.nutag.toml` next to the repository.
+//! Everything is optional, flags given on the command line always win.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use nanoserde::{Toml, TomlParser};
+
+use crate::forge::Forge;
+use crate::{BumpKind, Scheme, TagStyle};
+
+pub const FILE_NAME: &str = ".nutag.toml";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    /// The prefix used when none is given, e.g. `api` for `api@v1.2.3`
+    pub prefix: Option<String>,
+    /// The version of the very first tag in the repository
+    pub initial_version: Option<semver::Version>,
+    /// The prerelease identifier, e.g. `pre` for `v1.2.3-pre0`
+    pub pre_id: Option<String>,
+    /// Zero-pads prerelease counters, e.g. 3 for `v1.2.3-pre001`
+    pub pre_padding: Option<usize>,
+    /// Prereleases get the UTC time instead of a counter, e.g. `v1.2.3-pre.20250311143000`
+    pub pre_timestamp: Option<bool>,
+    /// The branch (or jj bookmark) releases are cut from
+    pub default_branch: Option<String>,
+    /// The bump used when no flags are given, e.g. `auto`
+    pub bump: Option<BumpKind>,
+    /// The annotation of new tags, e.g. `Release {version}`
+    pub message_template: Option<String>,
+    /// Whether tags are signed when neither `--sign` nor `--no-sign` is given
+    pub sign: Option<bool>,
+    /// The remote tags are fetched from and pushed to, `origin` if not set
+    pub remote: Option<String>,
+    /// The remote whose repository on the forge has the tags, `upstream` if there is one
+    pub api_remote: Option<String>,
+    /// Infer the prefix from the package in the current directory
+    pub auto_prefix: Option<bool>,
+    /// Whether new tags look like `v1.2.3` or `1.2.3`, following the latest tag if not set
+    pub tag_style: Option<TagStyle>,
+    /// Also consider tags like `V1.2.3` or `release-1.2.3`
+    pub lenient_tags: Option<bool>,
+    /// Parses tags in a custom scheme, with `major`, `minor` and `patch` groups
+    pub tag_regex: Option<String>,
+    /// Renders tags in a custom scheme, e.g. `myapp_v{major}_{minor}_{patch}`
+    pub tag_template: Option<String>,
+    /// `semver` or `calver`, semver if not set
+    pub scheme: Option<Scheme>,
+    /// The calver format, e.g. `YY.0M.MICRO`, see [`crate::calver::CalVer`]
+    pub calver_format: Option<String>,
+    /// Build metadata attached to new tags, e.g. `ci.{run_id}`
+    pub build: Option<String>,
+    /// The prerelease channels in order, `alpha`, `beta` and `rc` if not set
+    pub channels: Option<Vec<String>>,
+    /// Finds the ticket in a branch name for `{ticket}` in the prerelease identifier
+    pub ticket_regex: Option<String>,
+    /// How many dated tags `nutag nightly` keeps
+    pub nightly_keep: Option<usize>,
+    /// Always move the floating `v1` and `v1.4` tags, like `--aliases`
+    pub aliases: Option<bool>,
+    /// A tag that follows every release, like `--stable-tag`
+    pub stable_tag: Option<String>,
+    /// No tag may be below this version, e.g. because older tags were lost in a migration
+    pub min_version: Option<semver::Version>,
+    /// Channels that are release streams of their own, e.g. `lts` for `v1.8.12+lts`
+    pub channel_suffixes: Option<Vec<String>>,
+    /// The forge hosting the repository, for self-hosted instances that can't be told by their host
+    pub forge: Option<Forge>,
+    /// The API of a GitHub Enterprise Server, e.g. `https://github.mycorp.com/api`
+    pub github_api_url: Option<String>,
+    /// How often a rate-limited API call is tried again, 3 if not set
+    pub api_retries: Option<usize>,
+    /// PEM certificates to verify the forge with instead of the bundled ones, for internal CAs
+    pub ca_bundle: Option<String>,
+    /// Prints the GitHub token, e.g. `op read op://ci/github/token`, if it isn't in the environment
+    pub token_command: Option<String>,
+    /// The OAuth app `nutag auth login --device` signs in with
+    pub github_client_id: Option<String>,
+}
+
+impl Config {
+    /// Reads the closest `.nutag.toml` between the current directory and the root of the
+    /// repository, falling back to the defaults if there is none
+    pub fn load(root: Option<&Path>) -> Result<Self, anyhow::Error> {
+        let cwd = std::env::current_dir()?;
+        let mut found = None;
+        for dir in cwd.ancestors() {
+            let path = dir.join(FILE_NAME);
+            if path.exists() {
+                found = Some(path);
+                break;
+            }
+            if root.is_none_or(|root| dir == root) {
+                break;
+            }
+        }
+        let Some(path) = found else {
+            log::debug!("No {FILE_NAME} found, using defaults");
+            return Ok(Self::default());
+        };
+
+        log::debug!("Reading {}", path.display());
+        let raw = std::fs::read_to_string(&path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        Self::parse(&raw).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, anyhow::Error> {
+        let toml = TomlParser::parse(raw).map_err(|e| anyhow!("{e}"))?;
+
+        let tag_regex = string(&toml, "tag_regex")?;
+        let tag_template = string(&toml, "tag_template")?;
+        if tag_regex.is_some() != tag_template.is_some() {
+            bail!("`tag_regex` and `tag_template` have to be set together");
+        }
+
+        Ok(Self {
+            prefix: string(&toml, "prefix")?,
+            initial_version: version(&toml, "initial_version")?,
+            pre_id: string(&toml, "pre_id")?,
+            pre_padding: number(&toml, "pre_padding")?,
+            pre_timestamp: boolean(&toml, "pre_timestamp")?,
+            default_branch: string(&toml, "default_branch")?,
+            bump: string(&toml, "bump")?
+                .map(|b| b.parse().map_err(|e| anyhow!("`bump`: {e}")))
+                .transpose()?,
+            message_template: string(&toml, "message_template")?,
+            sign: boolean(&toml, "sign")?,
+            remote: string(&toml, "remote")?,
+            api_remote: string(&toml, "api_remote")?,
+            auto_prefix: boolean(&toml, "auto_prefix")?,
+            tag_style: string(&toml, "tag_style")?
+                .map(|s| s.parse().map_err(|e| anyhow!("`tag_style`: {e}")))
+                .transpose()?,
+            lenient_tags: boolean(&toml, "lenient_tags")?,
+            tag_regex,
+            tag_template,
+            scheme: string(&toml, "scheme")?
+                .map(|s| s.parse().map_err(|e| anyhow!("`scheme`: {e}")))
+                .transpose()?,
+            calver_format: string(&toml, "calver_format")?,
+            build: string(&toml, "build")?,
+            channels: strings(&toml, "channels")?,
+            ticket_regex: string(&toml, "ticket_regex")?,
+            nightly_keep: number(&toml, "nightly_keep")?,
+            aliases: boolean(&toml, "aliases")?,
+            stable_tag: string(&toml, "stable_tag")?,
+            min_version: version(&toml, "min_version")?,
+            channel_suffixes: strings(&toml, "channel_suffixes")?,
+            forge: string(&toml, "forge")?
+                .map(|s| s.parse().map_err(|e| anyhow!("`forge`: {e}")))
+                .transpose()?,
+            github_api_url: string(&toml, "github_api_url")?,
+            api_retries: number(&toml, "api_retries")?,
+            ca_bundle: string(&toml, "ca_bundle")?,
+            token_command: string(&toml, "token_command")?,
+            github_client_id: string(&toml, "github_client_id")?,
+        })
+    }
+
+    /// Renders the configuration in the format [`Config::parse`] understands
+    pub fn to_toml(&self) -> String {
+        let mut out = String::new();
+        let mut entry = |key: &str, value: Option<String>| {
+            if let Some(value) = value {
+                let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+                let _ = writeln!(out, "{key} = \"{value}\"");
+            }
+        };
+
+        entry("prefix", self.prefix.clone());
+        entry(
+            "initial_version",
+            self.initial_version.as_ref().map(|v| v.to_string()),
+        );
+        entry(
+            "min_version",
+            self.min_version.as_ref().map(|v| v.to_string()),
+        );
+        entry("pre_id", self.pre_id.clone());
+        entry("default_branch", self.default_branch.clone());
+        entry("bump", self.bump.map(|b| b.to_string()));
+        entry("message_template", self.message_template.clone());
+        entry("remote", self.remote.clone());
+        entry("api_remote", self.api_remote.clone());
+        entry("tag_style", self.tag_style.map(|s| s.to_string()));
+        entry("tag_regex", self.tag_regex.clone());
+        entry("tag_template", self.tag_template.clone());
+        entry("scheme", self.scheme.map(|s| s.to_string()));
+        entry("forge", self.forge.map(|f| f.to_string()));
+        entry("github_api_url", self.github_api_url.clone());
+        entry("ca_bundle", self.ca_bundle.clone());
+        entry("token_command", self.token_command.clone());
+        entry("github_client_id", self.github_client_id.clone());
+        entry("calver_format", self.calver_format.clone());
+        entry("build", self.build.clone());
+        entry("ticket_regex", self.ticket_regex.clone());
+        entry("stable_tag", self.stable_tag.clone());
+        for (key, value) in [
+            ("pre_padding", self.pre_padding),
+            ("nightly_keep", self.nightly_keep),
+            ("api_retries", self.api_retries),
+        ] {
+            if let Some(value) = value {
+                let _ = writeln!(out, "{key} = {value}");
+            }
+        }
+        for (key, values) in [
+            ("channels", &self.channels),
+            ("channel_suffixes", &self.channel_suffixes),
+        ] {
+            if let Some(values) = values {
+                let values: Vec<String> = values.iter().map(|v| format!("\"{v}\"")).collect();
+                let _ = writeln!(out, "{key} = [{}]", values.join(", "));
+            }
+        }
+        for (key, value) in [
+            ("sign", self.sign),
+            ("auto_prefix", self.auto_prefix),
+            ("lenient_tags", self.lenient_tags),
+            ("pre_timestamp", self.pre_timestamp),
+            ("aliases", self.aliases),
+        ] {
+            if let Some(value) = value {
+                let _ = writeln!(out, "{key} = {value}");
+            }
+        }
+
+        out
+    }
+}
+
+fn string(toml: &BTreeMap<String, Toml>, key: &str) -> Result<Option<String>, anyhow::Error> {
+    match toml.get(key) {
+        None => Ok(None),
+        Some(Toml::Str(value)) => Ok(Some(value.clone())),
+        Some(other) => bail!("`{key}` must be a string, got {other:?}"),
+    }
+}
+
+fn version(
+    toml: &BTreeMap<String, Toml>,
+    key: &str,
+) -> Result<Option<semver::Version>, anyhow::Error> {
+    string(toml, key)?
+        .map(|v| {
+            semver::Version::parse(&v).map_err(|e| anyhow!("`{key}` is not a valid version: {e}"))
+        })
+        .transpose()
+}
+
+fn strings(toml: &BTreeMap<String, Toml>, key: &str) -> Result<Option<Vec<String>>, anyhow::Error> {
+    let Some(value) = toml.get(key) else {
+        return Ok(None);
+    };
+    let Toml::SimpleArray(values) = value else {
+        bail!("`{key}` must be a list of strings, got {value:?}");
+    };
+    values
+        .iter()
+        .map(|value| match value {
+            Toml::Str(value) => Ok(value.clone()),
+            other => bail!("`{key}` must be a list of strings, got {other:?}"),
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+fn number(toml: &BTreeMap<String, Toml>, key: &str) -> Result<Option<usize>, anyhow::Error> {
+    match toml.get(key) {
+        None => Ok(None),
+        Some(Toml::Num(value)) if *value >= 0.0 && value.fract() == 0.0 => {
+            Ok(Some(*value as usize))
+        }
+        Some(other) => bail!("`{key}` must be a whole number, got {other:?}"),
+    }
+}
+
+fn boolean(toml: &BTreeMap<String, Toml>, key: &str) -> Result<Option<bool>, anyhow::Error> {
+    match toml.get(key) {
+        None => Ok(None),
+        Some(Toml::Bool(value)) => Ok(Some(*value)),
+        Some(other) => bail!("`{key}` must be true or false, got {other:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+
+    #[test]
+    fn parses_all_keys() {
+        let config = Config::parse(
+            r#"
+            prefix = "api"
+            initial_version = "1.0.0"
+            pre_id = "rc"
+            pre_padding = 3
+            pre_timestamp = true
+            default_branch = "trunk"
+            bump = "auto"
+            message_template = "Release {version}"
+            sign = false
+            remote = "upstream"
+            auto_prefix = true
+            tag_style = "bare"
+            lenient_tags = true
+            scheme = "calver"
+            calver_format = "YY.0M.MICRO"
+            channels = ["alpha", "rc"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.prefix.as_deref(), Some("api"));
+        assert_eq!(config.initial_version, Some(semver::Version::new(1, 0, 0)));
+        assert_eq!(config.pre_id.as_deref(), Some("rc"));
+        assert_eq!(config.pre_padding, Some(3));
+        assert_eq!(config.pre_timestamp, Some(true));
+        assert_eq!(config.default_branch.as_deref(), Some("trunk"));
+        assert_eq!(config.bump, Some(crate::BumpKind::Auto));
+        assert_eq!(
+            config.message_template.as_deref(),
+            Some("Release {version}")
+        );
+        assert_eq!(config.sign, Some(false));
+        assert_eq!(config.remote.as_deref(), Some("upstream"));
+        assert_eq!(config.auto_prefix, Some(true));
+        assert_eq!(config.tag_style, Some(crate::TagStyle::Bare));
+        assert_eq!(config.lenient_tags, Some(true));
+        assert_eq!(config.scheme, Some(crate::Scheme::CalVer));
+        assert_eq!(config.calver_format.as_deref(), Some("YY.0M.MICRO"));
+        assert_eq!(
+            config.channels,
+            Some(vec!["alpha".to_string(), "rc".to_string()])
+        );
+    }
+
+    #[test]
+    fn rejects_values_of_the_wrong_type() {
+        assert!(Config::parse("prefix = true").is_err());
+        assert!(Config::parse(r#"initial_version = "one""#).is_err());
+        assert!(Config::parse(r#"min_version = "2""#).is_err());
+        assert!(Config::parse(r#"bump = "huge""#).is_err());
+        assert!(Config::parse(r#"tag_style = "V""#).is_err());
+        assert!(Config::parse(r#"tag_template = "v{major}""#).is_err());
+        assert!(Config::parse("channels = [1, 2]").is_err());
+        assert!(Config::parse("pre_padding = 1.5").is_err());
+        assert!(Config::parse(r#"sign = "yes""#).is_err());
+    }
+
+    #[test]
+    fn survives_a_round_trip() {
+        let config = Config {
+            prefix: Some("web\"client".into()),
+            initial_version: Some(semver::Version::new(0, 0, 1)),
+            pre_id: None,
+            pre_padding: Some(2),
+            pre_timestamp: None,
+            default_branch: Some("main".into()),
+            bump: Some(crate::BumpKind::Minor),
+            message_template: None,
+            sign: Some(true),
+            remote: None,
+            api_remote: Some("upstream".into()),
+            auto_prefix: Some(false),
+            tag_style: Some(crate::TagStyle::V),
+            lenient_tags: None,
+            tag_regex: Some(r"^app_(?P<major>\d+)$".into()),
+            tag_template: Some("app_{major}".into()),
+            scheme: Some(crate::Scheme::SemVer),
+            calver_format: None,
+            build: Some("ci.{run_id}".into()),
+            channels: Some(vec!["beta".into(), "rc".into()]),
+            ticket_regex: None,
+            nightly_keep: Some(14),
+            aliases: Some(true),
+            stable_tag: Some("stable".into()),
+            min_version: Some(semver::Version::new(2, 0, 0)),
+            channel_suffixes: Some(vec!["lts".into()]),
+            forge: Some(crate::forge::Forge::GitLab),
+            github_api_url: Some("https://github.mycorp.com/api".into()),
+            api_retries: Some(5),
+            ca_bundle: Some("certs/corp-ca.pem".into()),
+            token_command: Some("op read op://ci/github/token".into()),
+            github_client_id: Some("Iv1.0123456789abcdef".into()),
+        };
+
+        assert_eq!(Config::parse(&config.to_toml()).unwrap(), config);
+    }
+}
@@ -0,0 +1,79 @@
+//! Just enough calendar arithmetic to stamp changelogs and versions with today's (UTC) date,
+//! without pulling in a date library.
+
+use std::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// The current UTC time as `YYYYMMDDHHMMSS`, which sorts the same as a number or as text
+pub fn timestamp() -> String {
+    timestamp_at(now())
+}
+
+fn timestamp_at(secs: u64) -> String {
+    let date = Date::from_days((secs / 86_400) as i64);
+    let time = secs % 86_400;
+    format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}",
+        date.year,
+        date.month,
+        date.day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+impl Date {
+    pub fn today() -> Self {
+        Self::from_days((now() / 86_400) as i64)
+    }
+
+    /// Converts days since 1970-01-01 into a calendar date.
+    /// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+    pub fn from_days(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        Self { year, month, day }
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{timestamp_at, Date};
+
+    #[test]
+    fn converts_days_since_the_epoch() {
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days(11_016).to_string(), "2000-02-29");
+        assert_eq!(Date::from_days(20_158).to_string(), "2025-03-11");
+        assert_eq!(timestamp_at(20_158 * 86_400 + 52_200), "20250311143000");
+    }
+}
@@ -0,0 +1,95 @@
+//! Tags of repositories on Azure DevOps, via the refs API.
+
+use anyhow::{bail, Context};
+use base64::Engine;
+use log::info;
+use nanoserde::DeJson;
+
+use super::{find_token, percent_encode, send_cached, Forge, MissingToken, Repo};
+
+#[derive(Debug, DeJson)]
+struct Refs {
+    value: Vec<Ref>,
+}
+
+#[derive(Debug, DeJson)]
+struct Ref {
+    /// The full name, e.g. `refs/tags/v1.2.3`
+    name: String,
+}
+
+/// The organization, project and repository name, from any of the URL shapes Azure DevOps
+/// hands out for cloning
+pub(super) fn locate(repo: &Repo) -> Result<(String, String, String), anyhow::Error> {
+    let segments: Vec<&str> = repo.path.split('/').collect();
+    let located = match (repo.host.as_str(), &segments[..]) {
+        // https://dev.azure.com/org/project/_git/repo
+        ("dev.azure.com", [org, project, "_git", name]) => (*org, *project, *name),
+        // git@ssh.dev.azure.com:v3/org/project/repo
+        ("ssh.dev.azure.com" | "vs-ssh.visualstudio.com", ["v3", org, project, name]) => {
+            (*org, *project, *name)
+        }
+        // https://org.visualstudio.com/project/_git/repo, possibly in the DefaultCollection
+        (host, [.., project, "_git", name]) if host.ends_with(".visualstudio.com") => {
+            let org = host.trim_end_matches(".visualstudio.com");
+            (org, *project, *name)
+        }
+        _ => bail!(
+            "{}/{} is not an Azure DevOps repository, expected organization/project/_git/repository",
+            repo.host,
+            repo.path
+        ),
+    };
+    let (org, project, name) = located;
+    Ok((org.to_string(), project.to_string(), name.to_string()))
+}
+
+/// Azure DevOps takes a personal access token as the password of basic auth
+fn credentials(repo: &Repo) -> Result<String, MissingToken> {
+    let token = find_token(Forge::Azure, "AZURE_DEVOPS_EXT_PAT", repo)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(format!(":{token}")))
+}
+
+pub(super) fn tag_names(repo: &Repo) -> Result<Vec<String>, anyhow::Error> {
+    let credentials = credentials(repo)?;
+    let (org, project, name) = locate(repo)?;
+    info!("Going to fetch tags for {org}/{project}/{name} on Azure DevOps");
+
+    let mut names = Vec::new();
+    let mut continuation = String::new();
+    loop {
+        let mut url = format!(
+            "https://dev.azure.com/{org}/{project}/_apis/git/repositories/{name}/refs?filter=tags/&api-version=7.1"
+        );
+        // The tokens are base64, whose `+` would arrive as a space
+        if !continuation.is_empty() {
+            url = format!("{url}&continuationToken={}", percent_encode(&continuation));
+        }
+        let mut response = send_cached(&url, |agent| {
+            agent
+                .get(&url)
+                .header("Authorization", &format!("Basic {credentials}"))
+        })?;
+
+        // Only there if there are more refs
+        continuation = response
+            .headers()
+            .get("x-ms-continuationtoken")
+            .and_then(|token| token.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+
+        let body = response.body_mut().read_to_string()?;
+        let refs: Refs =
+            DeJson::deserialize_json(&body).context("to extract the tags from the response")?;
+        names.extend(refs.value.into_iter().filter_map(|r| {
+            r.name
+                .strip_prefix("refs/tags/")
+                .map(|name| name.to_string())
+        }));
+
+        if continuation.is_empty() {
+            return Ok(names);
+        }
+    }
+}
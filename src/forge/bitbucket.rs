@@ -0,0 +1,59 @@
+//! Tags of repositories on Bitbucket Cloud, via the 2.0 API.
+
+use anyhow::Context;
+use base64::Engine;
+use log::info;
+use nanoserde::DeJson;
+
+use super::{credential, env_token, send_cached, Forge, MissingToken, Repo};
+
+#[derive(Debug, DeJson)]
+struct Page {
+    values: Vec<Tag>,
+    /// The URL of the next page, missing on the last one
+    #[nserde(default)]
+    next: String,
+}
+
+#[derive(Debug, DeJson)]
+struct Tag {
+    name: String,
+}
+
+/// Bitbucket authenticates API calls with the username and an app password, the same pair
+/// `~/.netrc` or git's credential helper keep for HTTPS remotes
+fn credentials(repo: &Repo) -> Result<String, MissingToken> {
+    let from_env = env_token(Forge::Bitbucket, "BITBUCKET_USERNAME").and_then(|username| {
+        env_token(Forge::Bitbucket, "BITBUCKET_APP_PASSWORD").map(|password| (username, password))
+    });
+    let (username, password) = match from_env {
+        Ok(pair) => pair,
+        Err(missing) => credential(&repo.host).ok_or(missing)?,
+    };
+    Ok(base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}")))
+}
+
+pub(super) fn tag_names(repo: &Repo) -> Result<Vec<String>, anyhow::Error> {
+    let credentials = credentials(repo)?;
+    let (workspace, slug) = repo.owner_and_name()?;
+    info!("Going to fetch tags for {workspace}/{slug} on Bitbucket");
+
+    let mut names = Vec::new();
+    let mut url = format!(
+        "https://api.bitbucket.org/2.0/repositories/{workspace}/{slug}/refs/tags?pagelen=100"
+    );
+    while !url.is_empty() {
+        let mut response = send_cached(&url, |agent| {
+            agent
+                .get(&url)
+                .header("Authorization", &format!("Basic {credentials}"))
+        })?;
+
+        let body = response.body_mut().read_to_string()?;
+        let page: Page =
+            DeJson::deserialize_json(&body).context("to extract the tags from the response")?;
+        names.extend(page.values.into_iter().map(|tag| tag.name));
+        url = page.next;
+    }
+    Ok(names)
+}
@@ -0,0 +1,168 @@
+//! Pages of tags the forges answered with before, kept under `$XDG_CACHE_HOME/nutag` so
+//! later runs only ask whether they changed instead of downloading them again. GitHub's
+//! GraphQL API can't be asked that, so the tags it listed are kept as a whole instead.
+
+use std::path::PathBuf;
+
+use log::debug;
+use ureq::http::{HeaderMap, Response};
+use ureq::Body;
+
+use crate::digest::sha256_hex;
+
+/// A response together with the `ETag` the forge sent for it
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Entry {
+    pub etag: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Entry {
+    /// `etag`, one `name: value` line per header, a blank line and the body
+    fn encode(&self) -> Vec<u8> {
+        let mut out = format!("{}\n", self.etag);
+        for (name, value) in &self.headers {
+            out.push_str(&format!("{name}: {value}\n"));
+        }
+        out.push('\n');
+        let mut out = out.into_bytes();
+        out.extend_from_slice(&self.body);
+        out
+    }
+
+    fn decode(raw: &[u8]) -> Option<Self> {
+        let end = raw.windows(2).position(|w| w == b"\n\n")?;
+        let head = std::str::from_utf8(&raw[..end]).ok()?;
+        let mut lines = head.lines();
+        let etag = lines.next()?.to_string();
+        let headers = lines
+            .map(|line| {
+                let (name, value) = line.split_once(": ")?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect::<Option<_>>()?;
+        Some(Entry {
+            etag,
+            headers,
+            body: raw[end + 2..].to_vec(),
+        })
+    }
+
+    pub fn into_response(self) -> Result<Response<Body>, anyhow::Error> {
+        let mut response = Response::builder().status(200);
+        for (name, value) in &self.headers {
+            response = response.header(name, value);
+        }
+        Ok(response.body(Body::builder().data(self.body))?)
+    }
+}
+
+/// The tags a GraphQL query listed, along with how many there were in total
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Tags {
+    pub total: usize,
+    pub names: Vec<String>,
+}
+
+impl Tags {
+    /// The total on the first line, then one name per line
+    fn encode(&self) -> Vec<u8> {
+        let mut out = format!("{}\n", self.total);
+        for name in &self.names {
+            out.push_str(&format!("{name}\n"));
+        }
+        out.into_bytes()
+    }
+
+    fn decode(raw: &[u8]) -> Option<Self> {
+        let mut lines = std::str::from_utf8(raw).ok()?.lines();
+        Some(Tags {
+            total: lines.next()?.parse().ok()?,
+            names: lines.map(str::to_string).collect(),
+        })
+    }
+}
+
+fn dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join("nutag"))
+}
+
+/// Entries are named after the digest of their URL (or the key of a query), which can
+/// contain anything
+fn path(key: &str) -> Option<PathBuf> {
+    Some(dir()?.join(sha256_hex(key.as_bytes())))
+}
+
+/// The cache only saves time, so failing to write it is not worth an error
+fn write(key: &str, contents: &[u8]) {
+    let Some(path) = path(key) else {
+        return;
+    };
+    let written = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&path, contents));
+    if let Err(e) = written {
+        debug!("Could not cache {key} in {}: {e}", path.display());
+    }
+}
+
+pub(super) fn load(url: &str) -> Option<Entry> {
+    let raw = std::fs::read(path(url)?).ok()?;
+    Entry::decode(&raw)
+}
+
+pub(super) fn store(url: &str, etag: &str, headers: &HeaderMap, body: &[u8]) {
+    let entry = Entry {
+        etag: etag.to_string(),
+        headers: headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect(),
+        body: body.to_vec(),
+    };
+    write(url, &entry.encode());
+}
+
+pub(super) fn load_tags(key: &str) -> Option<Tags> {
+    let raw = std::fs::read(path(key)?).ok()?;
+    Tags::decode(&raw)
+}
+
+pub(super) fn store_tags(key: &str, tags: &Tags) {
+    write(key, &tags.encode());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Entry, Tags};
+
+    #[test]
+    fn survives_a_round_trip() {
+        let entry = Entry {
+            etag: r#"W/"5c9a""#.to_string(),
+            headers: vec![
+                (
+                    "link".to_string(),
+                    r#"<https://x/?page=2>; rel="next""#.to_string(),
+                ),
+                ("x-next-page".to_string(), "2".to_string()),
+            ],
+            body: b"[{\"name\": \"v1.0.0\"}]\n\n".to_vec(),
+        };
+
+        assert_eq!(Entry::decode(&entry.encode()), Some(entry));
+
+        let tags = Tags {
+            total: 2,
+            names: vec!["v1.1.0".to_string(), "v1.0.0".to_string()],
+        };
+        assert_eq!(Tags::decode(&tags.encode()), Some(tags));
+        assert_eq!(Tags::decode(b"not a number\nv1.0.0\n"), None);
+    }
+}
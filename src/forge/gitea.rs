@@ -0,0 +1,69 @@
+//! Tags of repositories on Gitea or Forgejo instances, Codeberg among them.
+
+use anyhow::Context;
+use log::info;
+use nanoserde::DeJson;
+
+use super::{fetch_pages, find_token, send_cached, Forge, MissingToken, Repo};
+
+/// The most Gitea returns per page by default
+const PAGE_SIZE: usize = 50;
+
+#[derive(Debug, DeJson)]
+struct Tag {
+    name: String,
+}
+
+fn token(repo: &Repo) -> Result<String, MissingToken> {
+    find_token(Forge::Gitea, "GITEA_TOKEN", repo)
+}
+
+pub(super) fn tag_names(repo: &Repo) -> Result<Vec<String>, anyhow::Error> {
+    let token = token(repo)?;
+    let (owner, name) = repo.owner_and_name()?;
+    info!("Going to fetch tags for {owner}/{name} on {}", repo.host);
+
+    let page = |page: usize| -> Result<(Vec<String>, Option<usize>), anyhow::Error> {
+        let url = format!(
+            "https://{}/api/v1/repos/{owner}/{name}/tags?limit={PAGE_SIZE}&page={page}",
+            repo.host
+        );
+        let mut response = send_cached(&url, |agent| {
+            agent
+                .get(&url)
+                .header("Authorization", &format!("token {token}"))
+        })?;
+
+        let total: Option<usize> = response
+            .headers()
+            .get("x-total-count")
+            .and_then(|count| count.to_str().ok())
+            .and_then(|count| count.parse().ok());
+
+        let body = response.body_mut().read_to_string()?;
+        let tags: Vec<Tag> =
+            DeJson::deserialize_json(&body).context("to extract the tags from the response")?;
+        Ok((tags.into_iter().map(|tag| tag.name).collect(), total))
+    };
+
+    let (mut names, total) = page(1)?;
+    if let Some(total) = total {
+        // Instances may return fewer than asked for, the first page tells how many
+        let pages = total.div_ceil(names.len().max(1));
+        for more in fetch_pages(2..=pages, |n| Ok(page(n)?.0))? {
+            names.extend(more);
+        }
+        return Ok(names);
+    }
+    // Older instances don't count the tags, so keep going until a page comes back short
+    let mut last = names.len();
+    for n in 2.. {
+        if last < PAGE_SIZE {
+            break;
+        }
+        let (more, _) = page(n)?;
+        last = more.len();
+        names.extend(more);
+    }
+    Ok(names)
+}
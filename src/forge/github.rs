@@ -0,0 +1,510 @@
+//! Tags and releases of repositories on GitHub.
+
+use std::process::Command;
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context};
+use log::{debug, info, warn};
+use nanoserde::{DeJson, SerJson};
+use semver::Version;
+
+use super::{
+    cache, credential_token, env_token, fetch_pages, send, send_cached, Forge, Repo, TagVersion,
+};
+use crate::{dry_run, github_api_url, keychain, TOKEN_COMMAND, TOKEN_FILE};
+use app::App;
+
+mod app;
+mod device;
+
+/// Where the GraphQL and REST APIs live
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Api {
+    pub graphql: String,
+    pub rest: String,
+}
+
+impl Api {
+    /// `api.github.com` for github.com. GitHub Enterprise Server has both APIs under
+    /// `https://{host}/api`, unless `github_api_url` in the config says otherwise.
+    pub(super) fn new(repo: &Repo, configured: Option<&str>) -> Self {
+        let base = match (configured, repo.host.as_str()) {
+            (Some(base), _) => base.trim_end_matches('/').to_string(),
+            (None, "github.com") => "https://api.github.com".to_string(),
+            (None, host) => format!("https://{host}/api"),
+        };
+        let rest = match base.as_str() {
+            "https://api.github.com" => base.clone(),
+            _ => format!("{base}/v3"),
+        };
+        Api {
+            graphql: format!("{base}/graphql"),
+            rest,
+        }
+    }
+}
+
+/// The most refs GraphQL returns at once
+const PAGE_SIZE: usize = 100;
+
+/// Stops runaway pagination on repositories with an enormous number of tags
+const MAX_PAGES: usize = 50;
+
+/// Tried in this order: `--token-file`, a GitHub App, `GH_TOKEN`, `GITHUB_TOKEN`,
+/// `token_command` in the config, the token from `nutag auth login`, the token the gh CLI
+/// signed in with, and at last the password `~/.netrc` or git's credential helper have
+fn token(repo: &Repo, api: &Api) -> Result<String, anyhow::Error> {
+    if let Some(path) = TOKEN_FILE.get() {
+        let token = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read the token file {path}"))?;
+        return non_empty(token, || format!("The token file {path} is empty"));
+    }
+
+    let (owner, name) = repo.owner_and_name()?;
+    // Listing tags and creating a release in one run shouldn't mint two tokens
+    static INSTALLATION_TOKEN: OnceLock<String> = OnceLock::new();
+    if let Some(token) = INSTALLATION_TOKEN.get() {
+        return Ok(token.clone());
+    }
+    if let Some(app) = App::from_env()? {
+        let token = app.installation_token(api, owner, name)?;
+        return Ok(INSTALLATION_TOKEN.get_or_init(|| token).clone());
+    }
+
+    // The same order as gh itself
+    if let Ok(token) = std::env::var("GH_TOKEN") {
+        return Ok(token);
+    }
+    let missing = match env_token(Forge::GitHub, "GITHUB_TOKEN") {
+        Ok(token) => return Ok(token),
+        Err(missing) => missing,
+    };
+    if let Some(command) = TOKEN_COMMAND.get() {
+        return command_token(command);
+    }
+    let token = keychain::load(&repo.host)
+        .or_else(|| gh_token(&repo.host))
+        .or_else(|| credential_token(&repo.host));
+    Ok(token.ok_or(missing)?)
+}
+
+/// The token printed by `token_command`, e.g. a secret manager's CLI
+pub(super) fn command_token(command: &str) -> Result<String, anyhow::Error> {
+    debug!("Running token_command `{command}`");
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let output = Command::new(shell)
+        .args([flag, command])
+        .output()
+        .with_context(|| format!("Could not run token_command `{command}`"))?;
+    if !output.status.success() {
+        bail!(
+            "token_command `{command}` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let token = String::from_utf8(output.stdout).context("token_command printed no text")?;
+    non_empty(token, || {
+        format!("token_command `{command}` printed no token")
+    })
+}
+
+fn non_empty(token: String, empty: impl FnOnce() -> String) -> Result<String, anyhow::Error> {
+    match token.trim() {
+        "" => bail!(empty()),
+        token => Ok(token.to_string()),
+    }
+}
+
+/// The token `gh auth login` stored for `host`, if gh is installed and signed in there
+fn gh_token(host: &str) -> Option<String> {
+    let output = Command::new("gh")
+        .args(["auth", "token", "--hostname", host])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let token = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    debug!("Using the token of the gh CLI for {host}");
+    (!token.is_empty()).then_some(token)
+}
+
+pub(super) fn tag_names(
+    repo: &Repo,
+    starts_with: Option<&str>,
+    version: Option<TagVersion>,
+) -> Result<Vec<String>, anyhow::Error> {
+    let (owner, name) = repo.owner_and_name()?;
+    let api = Api::new(repo, github_api_url());
+    let github_token = token(repo, &api)?;
+    info!("Going to fetch tags for {owner}/{name}");
+
+    // The server does the filtering, e.g. of the few `api@` tags in a monorepo
+    let starts_with = starts_with.unwrap_or_default();
+    let remote = Remote {
+        api: &api,
+        token: &github_token,
+        owner,
+        name,
+    };
+
+    match graphql_tag_names(&remote, starts_with, version) {
+        Err(e) if graphql_unavailable(&e) => {
+            warn!("The GraphQL API is unavailable ({e}), falling back to the REST API");
+            rest_tag_names(&remote, starts_with)
+        }
+        names => names,
+    }
+}
+
+/// What it takes to ask GitHub about a repository
+struct Remote<'a> {
+    api: &'a Api,
+    token: &'a str,
+    owner: &'a str,
+    name: &'a str,
+}
+
+/// Fine-grained tokens and some proxies don't get along with GraphQL. A 401 is a bad token,
+/// which REST would turn away just the same.
+pub(super) fn graphql_unavailable(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<ureq::Error>(),
+        Some(ureq::Error::StatusCode(403 | 500..))
+    )
+}
+
+/// Tags come by the date of their commit, newest first. That is not the order of their
+/// versions, a backport can come before the release it was cut from, so paging only stops
+/// early once a whole page is below the newest release seen on the ones before.
+///
+/// GraphQL can't answer conditionally, so the result is kept in the [`cache`]. While the first
+/// page and the total are the same as last time, so are the tags, and one request does.
+fn graphql_tag_names(
+    remote: &Remote,
+    starts_with: &str,
+    version: Option<TagVersion>,
+) -> Result<Vec<String>, anyhow::Error> {
+    let Remote {
+        api,
+        token: github_token,
+        owner,
+        name,
+    } = *remote;
+
+    #[derive(SerJson)]
+    struct GqlRequest<'a> {
+        query: &'static str,
+        variables: Variables<'a>,
+    }
+
+    #[derive(SerJson)]
+    struct Variables<'a> {
+        owner: &'a str,
+        name: &'a str,
+        #[nserde(rename = "refPrefix")]
+        ref_prefix: &'a str,
+        first: usize,
+        #[nserde(rename = "endCursor")]
+        end_cursor: Option<&'a str>,
+    }
+
+    let query = r#"
+          query ($owner: String!, $name: String!, $refPrefix: String!, $first: Int!, $endCursor: String) {
+            repository(owner: $owner, name: $name) {
+              refs(refPrefix: $refPrefix, first: $first, after: $endCursor, orderBy:{field: TAG_COMMIT_DATE, direction: DESC }) {
+                totalCount
+                 pageInfo {
+                  endCursor
+                  hasNextPage
+                }
+                nodes {
+                  name
+                }
+              }
+            }
+          }
+        "#;
+
+    let ref_prefix = format!("refs/tags/{starts_with}");
+    // Stopping early gives a different answer than listing all tags
+    let cache_key = format!(
+        "{} {owner}/{name} {ref_prefix} {}",
+        api.graphql,
+        if version.is_some() { "newest" } else { "all" }
+    );
+    let mut cached = cache::load_tags(&cache_key);
+
+    let mut names = Vec::new();
+    let mut total = 0;
+    let mut end_cursor = None;
+    let mut newest_release: Option<Version> = None;
+    for page in 1..=MAX_PAGES {
+        let body = nanoserde::SerJson::serialize_json(&GqlRequest {
+            query,
+            variables: Variables {
+                owner,
+                name,
+                ref_prefix: &ref_prefix,
+                first: PAGE_SIZE,
+                end_cursor: end_cursor.as_deref(),
+            },
+        });
+
+        debug!("The query is:\n{body}");
+
+        info!("Fetching page {page} of the tags...");
+        let mut response = send(|agent| {
+            agent
+                .post(&api.graphql)
+                .header("Accept", "application/vnd.github+json")
+                .header("Authorization", &format!("Bearer {github_token}"))
+                .header("X-GitHub-Api-Version", "2022-11-28")
+                .send(body.as_bytes())
+        })?;
+
+        if response.status() != 200 {
+            bail!("unexpected response from GitHub: {}", response.status());
+        }
+        let body = response.body_mut().read_to_string()?;
+
+        let gql: Graphql = nanoserde::DeJson::deserialize_json(&body)
+            .context("to extract ref data from response")?;
+        let refs = gql.data.repository.refs;
+        // Names are relative to the refPrefix
+        let page_names: Vec<String> = refs
+            .nodes
+            .into_iter()
+            .map(|node| format!("{starts_with}{}", node.name))
+            .collect();
+        total = refs.total_count;
+        // Only the first page is compared
+        if let Some(cached) = cached
+            .take()
+            .filter(|cached| cached.total == total && cached.names.starts_with(&page_names))
+        {
+            debug!("The tags of {owner}/{name} are unchanged since the last run");
+            return Ok(cached.names);
+        }
+        let versions: Vec<Version> = version
+            .map(|version| page_names.iter().filter_map(|name| version(name)).collect())
+            .unwrap_or_default();
+        names.extend(page_names);
+
+        if !refs.page_info.has_next_page {
+            break;
+        }
+        if only_older(&versions, newest_release.as_ref()) {
+            debug!("Page {page} only has tags older than the newest release, not fetching more");
+            break;
+        }
+        if page == MAX_PAGES {
+            warn!(
+                "Stopped after the newest {} tags, older ones are not considered",
+                MAX_PAGES * PAGE_SIZE
+            );
+        }
+        newest_release = versions
+            .into_iter()
+            .filter(|version| version.pre.is_empty())
+            .chain(newest_release)
+            .max();
+        end_cursor = refs.page_info.end_cursor;
+    }
+
+    cache::store_tags(
+        &cache_key,
+        &cache::Tags {
+            total,
+            names: names.clone(),
+        },
+    );
+    Ok(names)
+}
+
+/// Whether a page with tags of these `versions` is past the newest release. A page without
+/// any doesn't tell, the tags that count may still come.
+pub(super) fn only_older(versions: &[Version], newest_release: Option<&Version>) -> bool {
+    newest_release.is_some_and(|newest| {
+        !versions.is_empty() && versions.iter().all(|version| version < newest)
+    })
+}
+
+/// The tags from the REST API's matching refs, which filter by prefix like GraphQL's `refPrefix`
+fn rest_tag_names(remote: &Remote, starts_with: &str) -> Result<Vec<String>, anyhow::Error> {
+    #[derive(Debug, DeJson)]
+    struct Ref {
+        /// The full name, e.g. `refs/tags/v1.2.3`
+        #[nserde(rename = "ref")]
+        name: String,
+    }
+
+    let page = |page: usize| -> Result<(Vec<String>, Option<usize>), anyhow::Error> {
+        let url = format!(
+            "{}/repos/{}/{}/git/matching-refs/tags/{starts_with}?per_page={PAGE_SIZE}&page={page}",
+            remote.api.rest, remote.owner, remote.name
+        );
+        info!("Fetching page {page} of the tags...");
+        let mut response = send_cached(&url, |agent| {
+            agent
+                .get(&url)
+                .header("Accept", "application/vnd.github+json")
+                .header("Authorization", &format!("Bearer {}", remote.token))
+                .header("X-GitHub-Api-Version", "2022-11-28")
+        })?;
+
+        // Only there if there is more than one page
+        let last = response
+            .headers()
+            .get("link")
+            .and_then(|link| link.to_str().ok())
+            .and_then(last_page);
+
+        let body = response.body_mut().read_to_string()?;
+        let refs: Vec<Ref> =
+            nanoserde::DeJson::deserialize_json(&body).context("to extract the tags")?;
+        let names: Vec<String> = refs
+            .into_iter()
+            .filter_map(|r| r.name.strip_prefix("refs/tags/").map(str::to_string))
+            .collect();
+        Ok((names, last))
+    };
+
+    let (mut names, last) = page(1)?;
+    let mut last = last.unwrap_or(1);
+    if last > MAX_PAGES {
+        warn!(
+            "Stopped after {} tags, the rest are not considered",
+            MAX_PAGES * PAGE_SIZE
+        );
+        last = MAX_PAGES;
+    }
+    for more in fetch_pages(2..=last, |n| Ok(page(n)?.0))? {
+        names.extend(more);
+    }
+    Ok(names)
+}
+
+/// The number of the `rel="last"` page of a `Link` header, e.g. 9 for
+/// `<https://api.github.com/...&page=2>; rel="next", <https://api.github.com/...&page=9>; rel="last"`
+pub(super) fn last_page(link: &str) -> Option<usize> {
+    let url = link.split(',').find_map(|entry| {
+        let (url, rel) = entry.split_once(';')?;
+        (rel.trim() == r#"rel="last""#).then_some(url.trim())
+    })?;
+    let (_, query) = url.trim_end_matches('>').split_once('?')?;
+    query
+        .split('&')
+        .find_map(|param| param.strip_prefix("page="))
+        .and_then(|page| page.parse().ok())
+}
+
+/// A token from GitHub's device flow, authorized by the user in the browser
+pub(super) fn device_login(host: &str, client_id: &str) -> Result<String, anyhow::Error> {
+    device::login(host, client_id)
+}
+
+/// Creates a GitHub release for an already pushed tag, letting GitHub generate the notes.
+/// Returns the URL of the release.
+pub(super) fn create_release(
+    repo: &Repo,
+    tag: &str,
+    draft: bool,
+    prerelease: bool,
+) -> Result<String, anyhow::Error> {
+    let (owner, name) = repo.owner_and_name()?;
+    let api = Api::new(repo, github_api_url());
+    let github_token = token(repo, &api)?;
+
+    #[derive(SerJson)]
+    struct NewRelease<'a> {
+        tag_name: &'a str,
+        name: &'a str,
+        draft: bool,
+        prerelease: bool,
+        generate_release_notes: bool,
+    }
+
+    #[derive(DeJson)]
+    struct CreatedRelease {
+        html_url: String,
+    }
+
+    let body = nanoserde::SerJson::serialize_json(&NewRelease {
+        tag_name: tag,
+        name: tag,
+        draft,
+        prerelease,
+        generate_release_notes: true,
+    });
+
+    let url = format!("{}/repos/{owner}/{name}/releases", api.rest);
+    if dry_run() {
+        println!("POST {url} {body}");
+        return Ok(format!(
+            "https://{}/{owner}/{name}/releases/tag/{tag}",
+            repo.host
+        ));
+    }
+
+    info!("Creating GitHub release for {tag}");
+    let mut response = send(|agent| {
+        agent
+            .post(&url)
+            .header("Accept", "application/vnd.github+json")
+            .header("Authorization", &format!("Bearer {github_token}"))
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send(body.as_bytes())
+    })?;
+
+    let body = response.body_mut().read_to_string()?;
+    let created: CreatedRelease =
+        nanoserde::DeJson::deserialize_json(&body).context("to extract the release URL")?;
+
+    Ok(created.html_url)
+}
+
+#[derive(Debug, DeJson)]
+struct Graphql {
+    data: Data,
+}
+
+#[derive(Debug, DeJson)]
+struct Data {
+    repository: Repository,
+}
+
+#[derive(Debug, DeJson)]
+struct Repository {
+    refs: Refs,
+}
+
+#[derive(Debug, DeJson)]
+struct Refs {
+    #[nserde(rename = "totalCount")]
+    total_count: usize,
+    #[nserde(rename = "pageInfo")]
+    page_info: PageInfo,
+    nodes: Vec<Name>,
+}
+
+/// `null` without any refs. The alias keeps nanoserde from treating the field as optional,
+/// GraphQL always sends it.
+type Cursor = Option<String>;
+
+#[derive(Debug, DeJson)]
+struct PageInfo {
+    #[nserde(rename = "endCursor")]
+    end_cursor: Cursor,
+    #[nserde(rename = "hasNextPage")]
+    has_next_page: bool,
+}
+
+#[derive(Debug, DeJson)]
+struct Name {
+    name: String,
+}
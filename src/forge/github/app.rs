@@ -0,0 +1,160 @@
+//! Authenticating as a GitHub App, which trades a JWT signed with the app's private key for a
+//! short-lived token of its installation on the repository.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use log::info;
+use nanoserde::DeJson;
+use ring::rand::SystemRandom;
+use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+use ureq::tls::PemItem;
+
+use super::Api;
+use crate::forge::send;
+
+/// Who signed the JWT and while it is valid
+fn claims(id: &str, now: u64) -> String {
+    // App ids are numbers, client ids strings, GitHub takes either
+    let issuer = match id.parse::<u64>() {
+        Ok(id) => id.to_string(),
+        Err(_) => format!("\"{id}\""),
+    };
+    // Backdated a minute in case our clock is ahead of GitHub's
+    format!(
+        r#"{{"iat":{},"exp":{},"iss":{issuer}}}"#,
+        now - 60,
+        now + 540
+    )
+}
+
+pub(super) struct App {
+    id: String,
+    key: RsaKeyPair,
+}
+
+impl App {
+    /// Set up with `GITHUB_APP_ID` and `GITHUB_APP_PRIVATE_KEY`, which holds the PEM key the app
+    /// settings hand out, or the path to it
+    pub fn from_env() -> Result<Option<Self>, anyhow::Error> {
+        let Ok(id) = std::env::var("GITHUB_APP_ID") else {
+            return Ok(None);
+        };
+        let Ok(key) = std::env::var("GITHUB_APP_PRIVATE_KEY") else {
+            bail!("GITHUB_APP_ID is set, but there is no GITHUB_APP_PRIVATE_KEY to sign in with");
+        };
+        let pem = if key.trim_start().starts_with("-----BEGIN") {
+            key.into_bytes()
+        } else {
+            std::fs::read(&key)
+                .with_context(|| format!("Could not read the GitHub App's private key {key}"))?
+        };
+
+        let key = ureq::tls::parse_pem(&pem)
+            .find_map(|item| match item {
+                Ok(PemItem::PrivateKey(key)) => Some(key),
+                _ => None,
+            })
+            .context("GITHUB_APP_PRIVATE_KEY holds no private key")?;
+        // The app settings hand out PKCS#1 keys, converted ones may be PKCS#8
+        let key = RsaKeyPair::from_der(key.der())
+            .or_else(|_| RsaKeyPair::from_pkcs8(key.der()))
+            .map_err(|e| anyhow!("The GitHub App's private key is unusable: {e}"))?;
+
+        Ok(Some(App { id, key }))
+    }
+
+    /// Proves being the app for the next ten minutes, the longest GitHub allows
+    fn jwt(&self, now: u64) -> Result<String, anyhow::Error> {
+        let message = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","typ":"JWT"}"#),
+            URL_SAFE_NO_PAD.encode(claims(&self.id, now))
+        );
+
+        let mut signature = vec![0; self.key.public().modulus_len()];
+        self.key
+            .sign(
+                &RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                message.as_bytes(),
+                &mut signature,
+            )
+            .map_err(|_| anyhow!("Could not sign the GitHub App's JWT"))?;
+        Ok(format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    /// A token of the app's installation on `owner/name`, limited to that repository
+    pub fn installation_token(
+        &self,
+        api: &Api,
+        owner: &str,
+        name: &str,
+    ) -> Result<String, anyhow::Error> {
+        #[derive(DeJson)]
+        struct Installation {
+            id: u64,
+        }
+
+        #[derive(DeJson)]
+        struct AccessToken {
+            token: String,
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let authorization = format!("Bearer {}", self.jwt(now)?);
+
+        let url = format!("{}/repos/{owner}/{name}/installation", api.rest);
+        let mut response = send(|agent| {
+            agent
+                .get(&url)
+                .header("Accept", "application/vnd.github+json")
+                .header("Authorization", &authorization)
+                .header("X-GitHub-Api-Version", "2022-11-28")
+                .call()
+        })
+        .with_context(|| format!("GitHub App {} is not installed on {owner}/{name}", self.id))?;
+        let body = response.body_mut().read_to_string()?;
+        let installation: Installation =
+            DeJson::deserialize_json(&body).context("to extract the installation")?;
+
+        info!("Signing in as GitHub App {}", self.id);
+        let url = format!(
+            "{}/app/installations/{}/access_tokens",
+            api.rest, installation.id
+        );
+        let repositories = format!(r#"{{"repositories":["{name}"]}}"#);
+        let mut response = send(|agent| {
+            agent
+                .post(&url)
+                .header("Accept", "application/vnd.github+json")
+                .header("Authorization", &authorization)
+                .header("X-GitHub-Api-Version", "2022-11-28")
+                .send(repositories.as_bytes())
+        })
+        .context("to create an installation token")?;
+        let body = response.body_mut().read_to_string()?;
+        let token: AccessToken =
+            DeJson::deserialize_json(&body).context("to extract the installation token")?;
+        Ok(token.token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::claims;
+
+    #[test]
+    fn claims_to_be_the_app() {
+        assert_eq!(
+            claims("123456", 1_700_000_000),
+            r#"{"iat":1699999940,"exp":1700000540,"iss":123456}"#
+        );
+        assert_eq!(
+            claims("Iv1.0123456789abcdef", 1_700_000_000),
+            r#"{"iat":1699999940,"exp":1700000540,"iss":"Iv1.0123456789abcdef"}"#
+        );
+    }
+}
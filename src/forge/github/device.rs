@@ -0,0 +1,91 @@
+//! GitHub's device flow: the user enters a code on GitHub while nutag waits for the OAuth token
+//! that comes out of it.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
+use nanoserde::DeJson;
+use owo_colors::OwoColorize;
+
+use crate::forge::send;
+
+/// Classic OAuth scopes have nothing narrower that reaches the tags and releases of private
+/// repositories
+const SCOPE: &str = "repo";
+
+#[derive(DeJson)]
+struct DeviceCode {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+/// Either the token or why there is none yet
+#[derive(DeJson)]
+struct Poll {
+    #[nserde(default)]
+    access_token: String,
+    #[nserde(default)]
+    error: String,
+    /// Only sent with `slow_down`
+    #[nserde(default)]
+    interval: u64,
+}
+
+pub(super) fn login(host: &str, client_id: &str) -> Result<String, anyhow::Error> {
+    let url = format!("https://{host}/login/device/code");
+    let mut response = send(|agent| {
+        agent
+            .post(&url)
+            .header("Accept", "application/json")
+            .send_form([("client_id", client_id), ("scope", SCOPE)])
+    })
+    .with_context(|| format!("Could not start the device flow on {host}"))?;
+    let body = response.body_mut().read_to_string()?;
+    let code: DeviceCode = DeJson::deserialize_json(&body).context("to extract the device code")?;
+
+    println!(
+        "Open {} and enter the code {}",
+        code.verification_uri,
+        code.user_code.bold()
+    );
+
+    let url = format!("https://{host}/login/oauth/access_token");
+    let expires = Instant::now() + Duration::from_secs(code.expires_in);
+    let mut interval = Duration::from_secs(code.interval);
+    loop {
+        std::thread::sleep(interval);
+        if Instant::now() > expires {
+            bail!("The code expired before it was entered, run nutag auth login --device again");
+        }
+
+        let mut response = send(|agent| {
+            agent
+                .post(&url)
+                .header("Accept", "application/json")
+                .send_form([
+                    ("client_id", client_id),
+                    ("device_code", code.device_code.as_str()),
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ])
+        })?;
+        let body = response.body_mut().read_to_string()?;
+        let poll: Poll = DeJson::deserialize_json(&body).context("to extract the token")?;
+
+        match poll.error.as_str() {
+            "" => return Ok(poll.access_token),
+            "authorization_pending" => {}
+            // GitHub asks for five more seconds each time
+            "slow_down" => {
+                interval = Duration::from_secs(poll.interval).max(interval + Duration::from_secs(5))
+            }
+            "expired_token" => {
+                bail!("The code expired before it was entered, run nutag auth login --device again")
+            }
+            "access_denied" => bail!("The login was cancelled on {host}"),
+            other => bail!("{host} refused the login: {other}"),
+        }
+    }
+}
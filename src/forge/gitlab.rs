@@ -0,0 +1,78 @@
+//! Tags of projects on gitlab.com or a self-hosted GitLab, via the REST API.
+
+use anyhow::Context;
+use log::info;
+use nanoserde::DeJson;
+
+use super::{fetch_pages, find_token, percent_encode, send_cached, Forge, MissingToken, Repo};
+
+#[derive(Debug, DeJson)]
+struct Tag {
+    name: String,
+}
+
+/// What GitLab says about the pages of tags
+struct Pages {
+    total: Option<usize>,
+    next: String,
+}
+
+fn token(repo: &Repo) -> Result<String, MissingToken> {
+    find_token(Forge::GitLab, "GITLAB_TOKEN", repo)
+}
+
+/// GitLab accepts the full path of a project as its id, with the slashes between groups,
+/// subgroups and the project encoded
+pub(super) fn project_id(repo: &Repo) -> String {
+    percent_encode(&repo.path)
+}
+
+pub(super) fn tag_names(repo: &Repo) -> Result<Vec<String>, anyhow::Error> {
+    let token = token(repo)?;
+    info!("Going to fetch tags for {} on {}", repo.path, repo.host);
+
+    let page = |page: &str| -> Result<(Vec<String>, Pages), anyhow::Error> {
+        let url = format!(
+            "https://{}/api/v4/projects/{}/repository/tags?per_page=100&page={page}",
+            repo.host,
+            project_id(repo)
+        );
+        let mut response = send_cached(&url, |agent| {
+            agent.get(&url).header("PRIVATE-TOKEN", &token)
+        })?;
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let pages = Pages {
+            // Left out for projects with more than 10,000 tags
+            total: header("x-total-pages").parse().ok(),
+            // Empty on the last page
+            next: header("x-next-page"),
+        };
+
+        let body = response.body_mut().read_to_string()?;
+        let tags: Vec<Tag> =
+            DeJson::deserialize_json(&body).context("to extract the tags from the response")?;
+        Ok((tags.into_iter().map(|tag| tag.name).collect(), pages))
+    };
+
+    let (mut names, mut pages) = page("1")?;
+    if let Some(total) = pages.total {
+        for more in fetch_pages(2..=total, |n| Ok(page(&n.to_string())?.0))? {
+            names.extend(more);
+        }
+        return Ok(names);
+    }
+    while !pages.next.is_empty() {
+        let (more, next) = page(&pages.next)?;
+        names.extend(more);
+        pages = next;
+    }
+    Ok(names)
+}
@@ -0,0 +1,96 @@
+//! Credentials from `~/.netrc` (or the file `$NETRC` points to), where curl and many CI images
+//! keep them.
+
+use std::path::PathBuf;
+
+use log::debug;
+
+/// The login and password of the `machine` entry for `host`. The `default` entry is meant for
+/// anonymous FTP and is no token for an API, so it never counts.
+pub(super) fn credential(host: &str) -> Option<(String, String)> {
+    let path = std::env::var_os("NETRC")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".netrc")))?;
+    let netrc = std::fs::read_to_string(&path).ok()?;
+    let credential = find(&netrc, host)?;
+    debug!("Using the credential for {host} from {}", path.display());
+    Some(credential)
+}
+
+#[derive(Default)]
+struct Entry<'a> {
+    /// `None` for the `default` entry, kept so its login and password don't end up on the
+    /// entry before it
+    machine: Option<&'a str>,
+    login: Option<&'a str>,
+    password: Option<&'a str>,
+}
+
+fn find(netrc: &str, host: &str) -> Option<(String, String)> {
+    // Macros run until the next blank line and can contain anything, so they go first
+    let mut tokens = Vec::new();
+    let mut in_macro = false;
+    for line in netrc.lines() {
+        if in_macro {
+            in_macro = !line.trim().is_empty();
+            continue;
+        }
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        for token in line.split_whitespace() {
+            if token == "macdef" {
+                in_macro = true;
+                break;
+            }
+            tokens.push(token);
+        }
+    }
+
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut tokens = tokens.into_iter();
+    while let Some(token) = tokens.next() {
+        match (token, entries.last_mut()) {
+            ("machine", _) => entries.push(Entry {
+                machine: Some(tokens.next()?),
+                ..Entry::default()
+            }),
+            ("default", _) => entries.push(Entry::default()),
+            ("login", Some(entry)) => entry.login = tokens.next(),
+            ("password", Some(entry)) => entry.password = tokens.next(),
+            ("account", _) => {
+                tokens.next();
+            }
+            _ => {}
+        }
+    }
+
+    let entry = entries.iter().find(|entry| entry.machine == Some(host))?;
+    Some((entry.login?.to_string(), entry.password?.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find;
+
+    #[test]
+    fn finds_the_entry_of_a_host() {
+        let netrc = "\
+# written by the CI image
+machine gitlab.com login ci password glpat-123
+macdef init
+machine github.com login evil password not-this-one
+
+machine github.com
+  login felipe
+  password ghp_456
+default login anonymous password guest
+";
+        let credential =
+            |login: &str, password: &str| Some((login.to_string(), password.to_string()));
+        assert_eq!(find(netrc, "github.com"), credential("felipe", "ghp_456"));
+        assert_eq!(find(netrc, "gitlab.com"), credential("ci", "glpat-123"));
+        assert_eq!(find(netrc, "codeberg.org"), None);
+        assert_eq!(find("machine github.com login felipe", "github.com"), None);
+    }
+}
@@ -0,0 +1,121 @@
+//! Tags of repositories on git.sr.ht, via its GraphQL API.
+
+use anyhow::{bail, Context};
+use log::{debug, info};
+use nanoserde::{DeJson, SerJson};
+
+use super::{find_token, send, Forge, MissingToken, Repo};
+
+const QUERY: &str = r#"
+  query ($owner: String!, $name: String!, $cursor: Cursor) {
+    user(username: $owner) {
+      repository(name: $name) {
+        references(cursor: $cursor) {
+          results { name }
+          cursor
+        }
+      }
+    }
+  }
+"#;
+
+#[derive(SerJson)]
+struct GqlRequest<'a> {
+    query: &'static str,
+    variables: Variables<'a>,
+}
+
+#[derive(SerJson)]
+struct Variables<'a> {
+    owner: &'a str,
+    name: &'a str,
+    cursor: Option<&'a str>,
+}
+
+#[derive(Debug, DeJson)]
+struct Graphql {
+    data: Data,
+}
+
+#[derive(Debug, DeJson)]
+struct Data {
+    user: User,
+}
+
+#[derive(Debug, DeJson)]
+struct User {
+    repository: Repository,
+}
+
+#[derive(Debug, DeJson)]
+struct Repository {
+    references: References,
+}
+
+/// `null` on the last page. The alias keeps nanoserde from treating the field as optional,
+/// GraphQL always sends it.
+type Cursor = Option<String>;
+
+#[derive(Debug, DeJson)]
+struct References {
+    results: Vec<Reference>,
+    cursor: Cursor,
+}
+
+#[derive(Debug, DeJson)]
+struct Reference {
+    /// The full name, e.g. `refs/tags/v1.2.3`
+    name: String,
+}
+
+fn token(repo: &Repo) -> Result<String, MissingToken> {
+    find_token(Forge::Sourcehut, "SRHT_TOKEN", repo)
+}
+
+pub(super) fn tag_names(repo: &Repo) -> Result<Vec<String>, anyhow::Error> {
+    let token = token(repo)?;
+    let (owner, name) = repo.owner_and_name()?;
+    // Users are written as ~user in URLs, but not in the API
+    let owner = owner.trim_start_matches('~');
+    info!("Going to fetch tags for ~{owner}/{name} on sourcehut");
+
+    let mut names = Vec::new();
+    let mut cursor = None;
+    loop {
+        let body = SerJson::serialize_json(&GqlRequest {
+            query: QUERY,
+            variables: Variables {
+                owner,
+                name,
+                cursor: cursor.as_deref(),
+            },
+        });
+        debug!("The query is:\n{body}");
+
+        let mut response = send(|agent| {
+            agent
+                .post(format!("https://{}/query", repo.host))
+                .header("Authorization", &format!("Bearer {token}"))
+                .send(body.as_bytes())
+        })?;
+
+        let body = response.body_mut().read_to_string()?;
+        if body.contains(r#""errors""#) {
+            bail!("unexpected response from sourcehut: {body}");
+        }
+        let gql: Graphql =
+            DeJson::deserialize_json(&body).context("to extract the tags from the response")?;
+        let references = gql.data.user.repository.references;
+
+        names.extend(references.results.into_iter().filter_map(|r| {
+            r.name
+                .strip_prefix("refs/tags/")
+                .map(|name| name.to_string())
+        }));
+
+        match references.cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(names),
+        }
+    }
+}
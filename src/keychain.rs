@@ -0,0 +1,55 @@
+//! Tokens stored by `nutag auth login` in the platform's keychain: the Keychain on macOS, the
+//! Secret Service on Linux and the Credential Manager on Windows. Entries belong to the service
+//! `nutag` and are named after the forge's host.
+
+use anyhow::Context;
+use keyring::Entry;
+use log::debug;
+
+const SERVICE: &str = "nutag";
+
+pub fn store(host: &str, token: &str) -> Result<(), anyhow::Error> {
+    let entry = Entry::new(SERVICE, host).context("Could not open the keychain")?;
+    save(&entry, token)
+}
+
+/// The token stored for `host`, if there is a keychain and it has one
+pub fn load(host: &str) -> Option<String> {
+    let token = read(&Entry::new(SERVICE, host).ok()?)?;
+    debug!("Using the token for {host} from the keychain");
+    Some(token)
+}
+
+fn save(entry: &Entry, token: &str) -> Result<(), anyhow::Error> {
+    entry
+        .set_password(token.trim())
+        .context("Could not store the token in the keychain")
+}
+
+fn read(entry: &Entry) -> Option<String> {
+    match entry.get_password() {
+        Ok(token) => Some(token.trim().to_string()).filter(|token| !token.is_empty()),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            debug!("Could not read the keychain: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use keyring::Entry;
+
+    use super::{read, save, SERVICE};
+
+    #[test]
+    fn round_trips_a_stored_token() {
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+        let entry = Entry::new(SERVICE, "github.com").unwrap();
+        assert_eq!(read(&entry), None);
+
+        save(&entry, "ghp_abc123\n").unwrap();
+        assert_eq!(read(&entry).as_deref(), Some("ghp_abc123"));
+    }
+}
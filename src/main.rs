@@ -1,256 +1,3429 @@
+use std::collections::BTreeSet;
 use std::fmt::{Display, Write};
+use std::io::IsTerminal;
 use std::process::Command;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 
 use anyhow::{anyhow, bail, Context};
 use bpaf::*;
+use calver::CalVer;
+use config::Config;
 use dialoguer::theme::ColorfulTheme;
-use dialoguer::{Confirm, Input};
+use dialoguer::{Confirm, FuzzySelect, Input, Password, Select};
+use forge::Forge;
 use log::{debug, error, info, warn};
-use nanoserde::{DeJson, SerJson};
 use owo_colors::OwoColorize;
 use regex_lite::Regex;
 use semver::{BuildMetadata, Prerelease};
+use tag_format::TagFormat;
 
+mod auto_prefix;
+mod calver;
+mod changelog;
+mod config;
+mod date;
+mod digest;
+mod forge;
+mod keychain;
+mod self_update;
+mod tag_format;
+mod template;
+
+#[derive(Debug, Clone)]
+struct Options {
+    verbose: usize,
+    auto_prefix: bool,
+    remote: Option<String>,
+    api_remote: Option<String>,
+    forge: Option<Forge>,
+    proxy: Option<String>,
+    token_file: Option<String>,
+    git_dir: Option<String>,
+    backend: Option<Backend>,
+    scheme: Option<Scheme>,
+    dry_run: bool,
+    offline: bool,
+    yes: bool,
+    generate_manpage: bool,
+    cmd: Cmd,
+}
+
+#[derive(Debug, Clone)]
+enum Cmd {
+    Auth(AuthCmd),
+    Bump(BumpArgs),
+    Changelog(ChangelogArgs),
+    Completions(Shell),
+    Delete(DeleteArgs),
+    Describe(DescribeArgs),
+    Diff(DiffArgs),
+    Graduate(GraduateArgs),
+    Init,
+    Latest(LatestArgs),
+    List(ListArgs),
+    Next(VersionArgs),
+    Nightly(NightlyArgs),
+    Promote(PromoteArgs),
+    Push(PushArgs),
+    Release(ReleaseArgs),
+    Retag(RetagArgs),
+    SelfUpdate(SelfUpdateArgs),
+    Show(ShowArgs),
+    Status,
+    Sync(SyncArgs),
+    Undo(UndoArgs),
+    Verify(VerifyArgs),
+}
+
+#[derive(Debug, Clone)]
+enum AuthCmd {
+    Login(LoginArgs),
+}
+
+#[derive(Debug, Clone)]
+struct LoginArgs {
+    /// `None` means the host of the remote
+    host: Option<String>,
+    device: bool,
+    /// `None` leaves it to the config
+    client_id: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct BumpArgs {
+    version: VersionArgs,
+    changelog: bool,
+    message: Option<Message>,
+    lightweight: bool,
+    all_tags: bool,
+    force: bool,
+    no_push: bool,
+    /// `None` leaves it to the config
+    sign: Option<bool>,
+    release: bool,
+    /// Also move the floating `v1` and `v1.4` tags
+    aliases: bool,
+    /// A tag like `stable` that follows the newest release
+    stable_tag: Option<String>,
+}
+
+/// Everything that goes into computing the next version, shared by `bump` and `next`
 #[derive(Debug, Clone)]
-struct Args {
+struct VersionArgs {
     major: bool,
     minor: bool,
     patch: bool,
     pre: bool,
-    verbose: usize,
+    pre_id: Option<String>,
+    /// Zero-pads the prerelease counter, e.g. 3 for `pre001`
+    pre_padding: Option<usize>,
+    /// Prereleases get the current time instead of a counter
+    timestamp: bool,
+    /// A prerelease in this channel, e.g. `beta` for `-beta.0`
+    channel: Option<String>,
+    /// One tag is planned per prefix, none means the configured one
+    prefixes: Vec<String>,
+    reference: Option<String>,
+    /// Tag the head of this branch instead of the current one
+    branch: Option<String>,
+    bump: Option<BumpKind>,
+    /// An exact version, bypassing the flags above
+    set: Option<String>,
+    /// The tag to bump from instead of the latest one
+    from: Option<String>,
+    /// Pick the tag to bump from instead of using the latest one
+    from_select: bool,
+    /// Bump from the latest tag reachable from the commit rather than the latest overall
+    maintenance: bool,
+    /// Build metadata to attach, a template like `ci.{run_id}`
+    build: Option<String>,
+    /// Where the first tag starts when there are none yet
+    initial_version: Option<semver::Version>,
+}
+
+/// Where the annotation of a new tag comes from
+#[derive(Debug, Clone)]
+enum Message {
+    Text(String),
+    /// See [`template::render`]
+    Template(String),
+}
+
+/// Which part of the version to bump, `Auto` decides based on the commit messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum BumpKind {
+    Patch,
+    Minor,
+    Major,
+    Auto,
+}
+
+impl FromStr for BumpKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "major" => Ok(BumpKind::Major),
+            "minor" => Ok(BumpKind::Minor),
+            "patch" => Ok(BumpKind::Patch),
+            "auto" => Ok(BumpKind::Auto),
+            other => Err(format!(
+                "unknown bump {other}, expected major, minor, patch or auto"
+            )),
+        }
+    }
+}
+
+impl Display for BumpKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BumpKind::Major => "major",
+            BumpKind::Minor => "minor",
+            BumpKind::Patch => "patch",
+            BumpKind::Auto => "auto",
+        })
+    }
+}
+
+/// How versions are counted up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Scheme {
+    #[default]
+    SemVer,
+    /// Versions are release dates, see [`calver::CalVer`]
+    CalVer,
+    /// Semver, but the major version is the year, e.g. `2025.3.1`
+    Hybrid,
+    /// `1.2.3.4`, where the version flags bump the last three components
+    FourPart,
+    /// Tags like `build-1234`, counting up by one
+    Build,
+}
+
+impl FromStr for Scheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "semver" => Ok(Scheme::SemVer),
+            "calver" => Ok(Scheme::CalVer),
+            "hybrid" => Ok(Scheme::Hybrid),
+            "four-part" => Ok(Scheme::FourPart),
+            "build" => Ok(Scheme::Build),
+            other => Err(format!(
+                "unknown scheme {other}, expected semver, calver, hybrid, four-part or build"
+            )),
+        }
+    }
+}
+
+impl Display for Scheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Scheme::SemVer => "semver",
+            Scheme::CalVer => "calver",
+            Scheme::Hybrid => "hybrid",
+            Scheme::FourPart => "four-part",
+            Scheme::Build => "build",
+        })
+    }
+}
+
+/// Whether the version in a tag name starts with a `v`, e.g. `v1.2.3` or plain `1.2.3`,
+/// or follows the configured [`TagFormat`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+enum TagStyle {
+    #[default]
+    V,
+    Bare,
+    Custom,
+}
+
+impl FromStr for TagStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "v" => Ok(TagStyle::V),
+            "bare" => Ok(TagStyle::Bare),
+            other => Err(format!("unknown tag style {other}, expected v or bare")),
+        }
+    }
+}
+
+impl Display for TagStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TagStyle::V => "v",
+            TagStyle::Bare => "bare",
+            TagStyle::Custom => "custom",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ChangelogArgs {
+    version: VersionArgs,
+    write: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Elvish,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "elvish" => Ok(Shell::Elvish),
+            other => Err(format!(
+                "unsupported shell {other}, expected bash, zsh, fish or elvish"
+            )),
+        }
+    }
+}
+
+/// Which toolchain nutag uses in a repository that jj and git share
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Backend {
+    /// jj if there is a `.jj` directory, git otherwise
+    #[default]
+    Auto,
+    Git,
+    Jj,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Backend::Auto),
+            "git" => Ok(Backend::Git),
+            "jj" => Ok(Backend::Jj),
+            other => Err(format!("unknown backend {other}, expected git, jj or auto")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DeleteArgs {
     no_push: bool,
-    no_sign: bool,
+    tag: String,
+}
+
+#[derive(Debug, Clone)]
+struct DescribeArgs {
     prefix: Option<String>,
     reference: Option<String>,
 }
 
-fn args() -> OptionParser<Args> {
-    let major = short('M')
-        .long("major")
-        .help("suggest the next major version")
+#[derive(Debug, Clone)]
+struct DiffArgs {
+    prefix: Option<String>,
+    reference: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct LatestArgs {
+    prefix: Option<String>,
+    release: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ListArgs {
+    prefix: Option<String>,
+    commits: bool,
+}
+
+#[derive(Debug, Clone)]
+struct GraduateArgs {
+    /// Tag `1.0.0-rc.0` first instead of going straight to `1.0.0`
+    rc: bool,
+    message: Option<Message>,
+    no_push: bool,
+    /// `None` leaves it to the config
+    sign: Option<bool>,
+    prefix: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct PromoteArgs {
+    message: Option<Message>,
+    no_push: bool,
+    /// `None` leaves it to the config
+    sign: Option<bool>,
+    prefix: Option<String>,
+    aliases: bool,
+    stable_tag: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct PushArgs {
+    all_tags: bool,
+    tag: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct ReleaseArgs {
+    draft: bool,
+    prerelease: bool,
+    prefix: Option<String>,
+    tag: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct NightlyArgs {
+    /// Move a single `nightly` tag instead of creating a dated one
+    floating: bool,
+    /// How many dated nightly tags to keep, `None` leaves it to the config
+    keep: Option<usize>,
+    no_push: bool,
+    /// `None` leaves it to the config
+    sign: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+struct RetagArgs {
+    commit: Option<String>,
+    no_push: bool,
+    /// `None` leaves it to the config
+    sign: Option<bool>,
+    tag: String,
+}
+
+#[derive(Debug, Clone)]
+struct SelfUpdateArgs {
+    check: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ShowArgs {
+    tag: String,
+}
+
+#[derive(Debug, Clone)]
+struct SyncArgs {
+    push: bool,
+}
+
+#[derive(Debug, Clone)]
+struct UndoArgs {
+    no_push: bool,
+}
+
+#[derive(Debug, Clone)]
+struct VerifyArgs {
+    prefix: Option<String>,
+    tag: Option<String>,
+}
+
+fn options() -> OptionParser<Options> {
+    let verbose = short('v')
+        .long("verbose")
+        .help("Increase the verbosity\n You can specify it up to 3 times\n either as -v -v -v or as -vvv")
+        .req_flag(())
+        .many()
+        .map(|xs| xs.len())
+        .guard(|&x| x <= 3, "It doesn't get any more verbose than this");
+
+    let auto_prefix = long("auto-prefix")
+        .help("infer the prefix from the package in the current directory of a monorepo")
         .switch();
 
-    let minor = short('m')
-        .long("minor")
-        .help("suggest the next minor version")
+    let remote = long("remote")
+        .help("the remote to fetch tags from and push them to (defaults to origin)")
+        .argument::<String>("REMOTE")
+        .optional();
+
+    let api_remote = long("api-remote")
+        .help("the remote whose repository on the forge has the tags (defaults to --remote, or upstream if there is one)")
+        .argument::<String>("REMOTE")
+        .optional();
+
+    let forge = long("forge")
+        .help("github, gitlab, gitea, bitbucket, azure or sourcehut, for self-hosted instances on domains of their own (detected from the remote otherwise)")
+        .argument::<Forge>("FORGE")
+        .optional();
+
+    let proxy = long("proxy")
+        .help("the proxy to talk to the forge through, e.g. http://proxy.corp:8080 (HTTPS_PROXY, HTTP_PROXY and NO_PROXY are respected otherwise)")
+        .argument::<String>("URL")
+        .optional();
+
+    let token_file = long("token-file")
+        .help("read the GitHub token from FILE instead of GH_TOKEN or GITHUB_TOKEN")
+        .argument::<String>("FILE")
+        .optional();
+
+    let git_dir = long("git-dir")
+        .help("the git repository to work on, e.g. a bare one on a release server (defaults to GIT_DIR or the current directory)")
+        .argument::<String>("DIR")
+        .optional();
+
+    let backend = long("backend")
+        .help("git or jj, for repositories that are both (auto, the default, prefers jj)")
+        .argument::<Backend>("BACKEND")
+        .optional();
+
+    let scheme = long("scheme")
+        .help("semver (the default), calver, which versions by release date (see calver_format), hybrid, where the major version is the year, four-part for 1.2.3.4 or build for build-1234")
+        .argument::<Scheme>("SCHEME")
+        .optional();
+
+    let dry_run = long("dry-run")
+        .help("print the git commands that would change tags instead of running them")
         .switch();
 
-    let patch = short('p')
-        .long("patch")
-        .help("suggest the next patch version")
+    let offline = long("offline")
+        .help("don't fetch, push or ask the forge, only the local tags are considered and tags only on the remote may be missing")
         .switch();
 
-    let pre = long("pre")
-        .help("suggest the next prerelease version")
+    let yes = short('y')
+        .long("yes")
+        .help("accept the suggested tag and answer yes to every question")
         .switch();
 
-    let verbose = short('v')
-        .long("verbose")
-        .help("Increase the verbosity\n You can specify it up to 3 times\n either as -v -v -v or as -vvv")
-        .req_flag(())
-        .many()
-        .map(|xs| xs.len())
-        .guard(|&x| x <= 3, "It doesn't get any more verbose than this");
+    let generate_manpage = long("generate-manpage")
+        .help("print the man page and exit")
+        .switch()
+        .hide();
+
+    let login = login_args()
+        .to_options()
+        .descr("Store a token for the forge in the keychain")
+        .header(
+            "The token is read from the terminal, or from stdin, e.g. `gh auth token | nutag auth login`. \
+             With --device nutag gets one from GitHub once the code it prints is entered in the browser. \
+             Later runs use it when the forge's environment variable isn't set.",
+        )
+        .command("login")
+        .map(AuthCmd::Login);
+
+    let auth = construct!([login])
+        .to_options()
+        .descr("Manage the tokens nutag talks to the forge with")
+        .command("auth")
+        .map(Cmd::Auth);
+
+    let bump = bump_args()
+        .to_options()
+        .descr("Suggest the next version, create the tag and push it")
+        .header(
+            "On main/master the next patch version is suggested, on any other branch the next \
+             prerelease. The suggestion can be edited before the tag is created, signed and pushed.",
+        )
+        .command("bump")
+        .map(Cmd::Bump);
+
+    let changelog = changelog_args()
+        .to_options()
+        .descr("Generate a changelog for the commits since the latest tag")
+        .command("changelog")
+        .map(Cmd::Changelog);
+
+    let completions = positional::<Shell>("SHELL")
+        .help("one of bash, zsh, fish or elvish")
+        .map(Cmd::Completions)
+        .to_options()
+        .descr("Print the shell completion script")
+        .command("completions");
+
+    let delete = delete_args()
+        .to_options()
+        .descr("Delete a tag locally and on the remote")
+        .command("delete")
+        .map(Cmd::Delete);
+
+    let describe = describe_args()
+        .to_options()
+        .descr("Describe a commit relative to the latest tag, like `git describe`")
+        .header(
+            "Prints the latest matching tag reachable from the commit, followed by the number of \
+             commits since and the abbreviated commit hash, e.g. v1.4.2-7-gabc1234. \
+             A tagged commit is described by its tag alone.",
+        )
+        .command("describe")
+        .map(Cmd::Describe);
+
+    let diff = diff_args()
+        .to_options()
+        .descr("List the commits since the latest tag")
+        .command("diff")
+        .map(Cmd::Diff);
+
+    let graduate = graduate_args()
+        .to_options()
+        .descr("Leave 0.x behind and tag 1.0.0, or a release candidate for it")
+        .command("graduate")
+        .map(Cmd::Graduate);
+
+    let init = pure(Cmd::Init)
+        .to_options()
+        .descr("Write a .nutag.toml configuring nutag for this project")
+        .command("init");
+
+    let latest = latest_args()
+        .to_options()
+        .descr("Print the latest matching tag")
+        .command("latest")
+        .map(Cmd::Latest);
+
+    let list = list_args()
+        .to_options()
+        .descr("List the tags nutag considers, sorted by version")
+        .command("list")
+        .map(Cmd::List);
+
+    let next = version_args()
+        .to_options()
+        .descr("Print the suggested next version without creating anything")
+        .command("next")
+        .map(Cmd::Next);
+
+    let nightly = nightly_args()
+        .to_options()
+        .descr("Tag the head of the default branch as today's nightly build")
+        .header(
+            "Creates nightly-YYYY-MM-DD, separate from the version tags, or moves a single \
+             nightly tag with --floating. --keep prunes the oldest dated nightly tags.",
+        )
+        .command("nightly")
+        .map(Cmd::Nightly);
+
+    let promote = promote_args()
+        .to_options()
+        .descr("Turn the latest prerelease into a final release on the same commit")
+        .command("promote")
+        .map(Cmd::Promote);
+
+    let push = push_args()
+        .to_options()
+        .descr("Push tags to the remote")
+        .command("push")
+        .map(Cmd::Push);
+
+    let release = release_args()
+        .to_options()
+        .descr("Create a GitHub release for a pushed tag")
+        .command("release")
+        .map(Cmd::Release);
+
+    let retag = retag_args()
+        .to_options()
+        .descr("Move an existing tag to a different commit")
+        .command("retag")
+        .map(Cmd::Retag);
+
+    let self_update = long("check")
+        .help("only check whether there is a newer version")
+        .switch()
+        .map(|check| Cmd::SelfUpdate(SelfUpdateArgs { check }))
+        .to_options()
+        .descr("Update nutag to the latest release")
+        .command("self-update");
+
+    let show = tag_arg("the tag to show")
+        .map(|tag| Cmd::Show(ShowArgs { tag }))
+        .to_options()
+        .descr("Show the commit, tagger, date and message of a tag")
+        .command("show");
+
+    let status = pure(Cmd::Status)
+        .to_options()
+        .descr("Compare local and remote tags and check whether HEAD is tagged")
+        .command("status");
+
+    let sync = sync_args()
+        .to_options()
+        .descr("Prune local tags that no longer exist on the remote and fetch new ones")
+        .command("sync")
+        .map(Cmd::Sync);
+
+    let undo = undo_args()
+        .to_options()
+        .descr("Delete the most recent tag created by nutag")
+        .command("undo")
+        .map(Cmd::Undo);
+
+    let verify = verify_args()
+        .to_options()
+        .descr("Check the signatures of release tags")
+        .command("verify")
+        .map(Cmd::Verify);
+
+    // Running `nutag` without a subcommand behaves like `nutag bump`
+    let default = bump_args().map(Cmd::Bump);
+
+    let cmd = construct!([
+        auth,
+        bump,
+        changelog,
+        completions,
+        delete,
+        describe,
+        diff,
+        graduate,
+        init,
+        latest,
+        list,
+        next,
+        nightly,
+        promote,
+        push,
+        release,
+        retag,
+        self_update,
+        show,
+        status,
+        sync,
+        undo,
+        verify,
+        default
+    ]);
+
+    construct!(Options {
+        verbose,
+        auto_prefix,
+        remote,
+        api_remote,
+        forge,
+        proxy,
+        token_file,
+        git_dir,
+        backend,
+        scheme,
+        dry_run,
+        offline,
+        yes,
+        generate_manpage,
+        cmd
+    })
+    .to_options()
+    .descr("Suggest the next version for tagging")
+    .header(
+        "nutag creates and manages semantic version tags in Git and Jujutsu repositories. \
+         It looks up the existing tags on GitHub (or GitLab, Gitea, Bitbucket and Azure DevOps), suggests the next version based on the flags \
+         and the current branch, and creates and pushes an annotated tag. \
+         Without a subcommand nutag behaves like `nutag bump`.",
+    )
+    .footer(
+        "The GitHub API is accessed with the token in $GITHUB_TOKEN, see the README for the other forges. \
+         Project defaults can be stored in .nutag.toml, see `nutag init`.",
+    )
+    .version(version())
+}
+
+/// nutag's own OAuth app on github.com, which has the device flow enabled. Set by the
+/// release workflow, client ids are public.
+const GITHUB_CLIENT_ID: Option<&str> = option_env!("NUTAG_GITHUB_CLIENT_ID");
+
+/// The version nutag was built as, set by the release workflow
+fn version() -> &'static str {
+    option_env!("TAG")
+        .and_then(|v| v.strip_prefix("v"))
+        .unwrap_or("unknwon")
+}
+
+fn prefix_arg(help: &'static str) -> impl Parser<String> {
+    long("prefix")
+        .help(help)
+        .argument::<String>("PREFIX")
+        .complete(|input: &String| complete_prefix(input))
+}
+
+fn aliases_arg() -> impl Parser<bool> {
+    long("aliases")
+        .help("also move the floating major and minor tags, e.g. v1 and v1.4, to the release")
+        .switch()
+}
+
+fn stable_tag_arg() -> impl Parser<Option<String>> {
+    long("stable-tag")
+        .help("also move this tag, e.g. stable or latest, to the release")
+        .argument::<String>("TAG")
+        .optional()
+}
+
+fn all_tags_arg() -> impl Parser<bool> {
+    long("all-tags")
+        .help("push every local tag, not just the new one")
+        .switch()
+}
+
+/// `--sign` or `--no-sign`, without either the config decides
+fn sign_arg() -> impl Parser<Option<bool>> {
+    let sign = long("sign")
+        .help("sign the tag with gpg (the default unless `sign = false` is configured)")
+        .req_flag(true);
+    let no_sign = long("no-sign").help("Don't sign the tag").req_flag(false);
+
+    construct!([sign, no_sign]).optional()
+}
+
+/// `-m` is taken by `--minor`, so there is only the long form
+fn message_arg() -> impl Parser<Option<Message>> {
+    let message = long("message")
+        .help("the annotation of the tag (defaults to \"Release <tag>\")")
+        .argument::<String>("MESSAGE")
+        .map(Message::Text);
+
+    let file = short('F')
+        .long("file")
+        .help("read the annotation of the tag from FILE, or from stdin with -")
+        .argument::<String>("FILE")
+        .parse(|path| read_message(&path).map_err(|e| format!("{e:#}")))
+        .map(Message::Text);
+
+    let template = long("message-template")
+        .help("the annotation with variables, e.g. \"Release {version} ({commit_short})\"; available are tag, version, prefix, commit, commit_short, date, author and commit_count")
+        .argument::<String>("TEMPLATE")
+        .map(Message::Template);
+
+    construct!([message, file, template]).optional()
+}
+
+fn read_message(path: &str) -> Result<String, anyhow::Error> {
+    if path == "-" {
+        return std::io::read_to_string(std::io::stdin()).context("reading the message from stdin");
+    }
+    std::fs::read_to_string(path).with_context(|| format!("reading the message from {path}"))
+}
+
+fn tag_arg(help: &'static str) -> impl Parser<String> {
+    positional::<String>("TAG")
+        .help(help)
+        .complete(|input: &String| complete_tag(input))
+}
+
+/// The prefix given on the command line, otherwise the configured one,
+/// otherwise the one inferred from the current directory if `--auto-prefix` is enabled
+fn resolve_prefix(
+    prefix: Option<String>,
+    config: &Config,
+) -> Result<Option<String>, anyhow::Error> {
+    if prefix.is_some() || config.prefix.is_some() {
+        return Ok(prefix.or(config.prefix.clone()));
+    }
+
+    let prefixes = local_prefixes()?;
+    if config.auto_prefix == Some(true) {
+        if let Some(prefix) = infer_prefix(&prefixes)? {
+            return Ok(Some(prefix));
+        }
+    }
+    pick_prefix(&prefixes)
+}
+
+/// The prefix of the package in the current directory, see [`auto_prefix::detect`]
+fn infer_prefix(prefixes: &BTreeSet<String>) -> Result<Option<String>, anyhow::Error> {
+    let cwd = std::env::current_dir()?.canonicalize()?;
+    let root = repo_root()?.canonicalize()?;
+    if cwd == root {
+        debug!("Not inferring a prefix at the root of the repository");
+        return Ok(None);
+    }
+
+    let detected = auto_prefix::detect(&cwd, prefixes);
+    match &detected {
+        Some(prefix) => info!("Using the prefix {prefix} for {}", cwd.display()),
+        None => warn!("Could not infer a prefix for {}", cwd.display()),
+    }
+    Ok(detected)
+}
+
+/// Asks which of the prefixes in use to work with, rather than silently ignoring all the
+/// prefixed tags. Scripts and repositories without prefixed tags get no prefix.
+fn pick_prefix(prefixes: &BTreeSet<String>) -> Result<Option<String>, anyhow::Error> {
+    if prefixes.is_empty() || non_interactive() {
+        return Ok(None);
+    }
+
+    let mut items = vec!["no prefix"];
+    items.extend(prefixes.iter().map(String::as_str));
+    let picked = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Which prefix?")
+        .items(&items)
+        .default(0)
+        .interact()?;
+
+    // The first item is "no prefix"
+    Ok(picked
+        .checked_sub(1)
+        .and_then(|idx| prefixes.iter().nth(idx))
+        .cloned())
+}
+
+/// The names of the tags `commit` contains, i.e. those on its history
+fn reachable_tag_names(commit: &str) -> Result<BTreeSet<String>, anyhow::Error> {
+    Ok(git(&["tag", "--merged", commit])?
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// The tag `--from` names, which takes the prefix in use unless it has one of its own
+fn find_from_tag(from: &str, prefix: Option<&str>, tags: &[Tag]) -> Result<Tag, anyhow::Error> {
+    let mut tag = Tag::try_from(from).with_context(|| format!("--from {from}"))?;
+    if tag.prefix.is_none() {
+        tag.prefix = prefix.map(str::to_string);
+    }
+    match tags.iter().find(|known| **known == tag) {
+        Some(known) => Ok(known.clone()),
+        None => bail!("There is no tag {tag} to bump from"),
+    }
+}
+
+/// How many tags `--from-select` shows at a time
+const RECENT_TAGS: usize = 20;
+
+/// Asks which of `tags` to bump from, most recent first and narrowed down as you type
+fn select_tag(tags: &[Tag]) -> Result<Tag, anyhow::Error> {
+    if non_interactive() {
+        bail!("--from-select needs a terminal to ask which tag to bump from");
+    }
+    if tags.is_empty() {
+        bail!("There are no tags to bump from");
+    }
+
+    let newest_first: Vec<&Tag> = tags.iter().rev().collect();
+    let picked = FuzzySelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Which tag to bump from?")
+        .items(&newest_first)
+        .default(0)
+        .max_length(RECENT_TAGS)
+        .interact()?;
+    Ok(newest_first[picked].clone())
+}
+
+/// The prefixes of the local tags, e.g. `api` for `api@v1.2.0`
+fn local_prefixes() -> Result<BTreeSet<String>, anyhow::Error> {
+    Ok(local_tag_names()?
+        .into_iter()
+        .filter_map(|name| Tag::try_from(name).ok()?.prefix)
+        .collect())
+}
+
+/// Suggests the prefixes of the local tags
+fn complete_prefix(input: &str) -> Vec<(String, Option<String>)> {
+    local_prefixes()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|prefix| prefix.starts_with(input))
+        .map(|prefix| (prefix, None))
+        .collect()
+}
+
+/// Suggests the local tags
+fn complete_tag(input: &str) -> Vec<(String, Option<String>)> {
+    local_tag_names()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|name| name.starts_with(input))
+        .map(|name| (name, None))
+        .collect()
+}
+
+fn bump_args() -> impl Parser<BumpArgs> {
+    let version = version_args();
+
+    let changelog = long("changelog")
+        .help("use the changelog since the latest tag as the tag message")
+        .switch();
+
+    let message = message_arg();
+
+    let lightweight = long("lightweight")
+        .help("create a lightweight tag, a plain ref without annotation or signature")
+        .switch();
+
+    let all_tags = all_tags_arg();
+
+    let force = long("force")
+        .help("replace the tag if it already exists, locally and on the remote")
+        .switch();
+
+    let no_push = long("no-push")
+        .help("create the tag locally but don't push it")
+        .switch();
+
+    let sign = sign_arg();
+
+    let release = long("release")
+        .help("create a GitHub release once the tag is pushed")
+        .switch();
+
+    let aliases = aliases_arg();
+
+    let stable_tag = stable_tag_arg();
+
+    construct!(BumpArgs {
+        version,
+        changelog,
+        message,
+        lightweight,
+        all_tags,
+        force,
+        no_push,
+        sign,
+        release,
+        aliases,
+        stable_tag,
+    })
+}
+
+fn changelog_args() -> impl Parser<ChangelogArgs> {
+    let version = version_args();
+
+    let write = long("write")
+        .help("add the changelog to CHANGELOG.md instead of printing it")
+        .switch();
+
+    construct!(ChangelogArgs { version, write })
+}
+
+fn version_args() -> impl Parser<VersionArgs> {
+    let major = short('M')
+        .long("major")
+        .help("suggest the next major version")
+        .switch();
+
+    let minor = short('m')
+        .long("minor")
+        .help("suggest the next minor version")
+        .switch();
+
+    let patch = short('p')
+        .long("patch")
+        .help("suggest the next patch version")
+        .switch();
+
+    let pre = long("pre")
+        .help("suggest the next prerelease version")
+        .switch();
+
+    let pre_id = long("pre-id")
+        .help("the identifier to use for prereleases (defaults to `pre`), end it with a dot for rc.0, rc.1, ..., {branch} is the current branch and {ticket} the ticket in its name")
+        .argument::<String>("ID")
+        .optional();
+
+    let pre_padding = long("pre-padding")
+        .help("zero-pad the prerelease counter to this many digits, e.g. 3 for pre001")
+        .argument::<usize>("WIDTH")
+        .optional();
+
+    let timestamp = long("timestamp")
+        .help("use the UTC time instead of a counter for prereleases, e.g. pre.20250311143000")
+        .switch();
+
+    let channel = long("channel")
+        .help("create a prerelease in this channel, e.g. beta for -beta.0 (see channels in the config), or a release in a stream of its own like lts for +lts (see channel_suffixes)")
+        .argument::<String>("CHANNEL")
+        .optional();
+
+    let prefixes = prefix_arg(
+        "a prefix to use when creating the tag, repeat it to tag several components at once",
+    )
+    .many();
+
+    // `--ref` is the original name and kept as an alias
+    let reference = short('r')
+        .long("commit")
+        .long("ref")
+        .help("the commit to tag instead of HEAD (or @/trunk() for jj), a git reference or jj revset (e.g. a release PR's merge commit, @-, a commit hash)")
+        .argument::<String>("REV")
+        .optional();
+
+    let branch = long("branch")
+        .help("tag the head of this branch (or jj bookmark), fetching it if needed")
+        .argument::<String>("BRANCH")
+        .optional();
+
+    let bump = long("bump")
+        .help("major, minor, patch or auto, which picks one based on the conventional commits since the latest tag")
+        .argument::<BumpKind>("KIND")
+        .optional();
+
+    let set = long("set")
+        .help("use exactly this version instead of suggesting one (e.g. 2.3.0)")
+        .argument::<String>("VERSION")
+        .optional();
+
+    let from = long("from")
+        .help("bump from this tag instead of the latest one, e.g. v1.3.0 to cut a hotfix for 1.3")
+        .argument::<String>("TAG")
+        .optional();
+
+    let from_select = long("from-select")
+        .help("pick the tag to bump from out of the recent ones, e.g. to cut a hotfix for an older version")
+        .switch();
+
+    let maintenance = long("maintenance")
+        .help("bump from the latest tag reachable from the current branch, and tag releases on it, e.g. v1.9.4 on a maintenance branch while main is at v2.3.0")
+        .switch();
+
+    let build = long("build")
+        .help("attach build metadata, can use {commit}, {commit_short}, {date} and {run_id} (e.g. ci.{run_id})")
+        .argument::<String>("BUILD")
+        .optional();
+
+    let initial_version = long("initial-version")
+        .help("the version to bump from when there are no tags yet, 0.1.0 by default")
+        .argument::<semver::Version>("VERSION")
+        .optional();
+
+    construct!(VersionArgs {
+        major,
+        minor,
+        patch,
+        pre,
+        pre_id,
+        pre_padding,
+        timestamp,
+        channel,
+        prefixes,
+        reference,
+        branch,
+        bump,
+        set,
+        from,
+        from_select,
+        maintenance,
+        build,
+        initial_version,
+    })
+}
+
+fn delete_args() -> impl Parser<DeleteArgs> {
+    let no_push = long("no-push")
+        .help("only delete the local tag, leave the remote alone")
+        .switch();
+
+    let tag = tag_arg("the tag to delete");
+
+    construct!(DeleteArgs { no_push, tag })
+}
+
+fn describe_args() -> impl Parser<DescribeArgs> {
+    let prefix = prefix_arg("only consider tags with this prefix").optional();
+
+    let reference = short('r')
+        .long("ref")
+        .help("git reference to describe (e.g. HEAD, @-, commit hash)")
+        .argument::<String>("REF")
+        .optional();
+
+    construct!(DescribeArgs { prefix, reference })
+}
+
+fn diff_args() -> impl Parser<DiffArgs> {
+    let prefix = prefix_arg("compare against the latest tag with this prefix").optional();
+
+    let reference = short('r')
+        .long("ref")
+        .help("git reference that would be tagged (e.g. HEAD, @-, commit hash)")
+        .argument::<String>("REF")
+        .optional();
+
+    construct!(DiffArgs { prefix, reference })
+}
+
+fn latest_args() -> impl Parser<LatestArgs> {
+    let prefix = prefix_arg("only consider tags with this prefix").optional();
+
+    let release = long("release")
+        .help("skip prereleases and print the latest release")
+        .switch();
+
+    construct!(LatestArgs { prefix, release })
+}
+
+fn list_args() -> impl Parser<ListArgs> {
+    let prefix = prefix_arg("only list tags with this prefix").optional();
+
+    let commits = long("commits")
+        .help("show the commit each tag points to")
+        .switch();
+
+    construct!(ListArgs { prefix, commits })
+}
+
+fn graduate_args() -> impl Parser<GraduateArgs> {
+    let rc = long("rc")
+        .help("tag a release candidate like 1.0.0-rc.0 first, promote it once it holds up")
+        .switch();
+
+    let message = message_arg();
+
+    let no_push = long("no-push")
+        .help("create the tag locally but don't push it")
+        .switch();
+
+    let sign = sign_arg();
+
+    let prefix = prefix_arg("graduate the package with this prefix").optional();
+
+    construct!(GraduateArgs {
+        rc,
+        message,
+        no_push,
+        sign,
+        prefix,
+    })
+}
+
+fn promote_args() -> impl Parser<PromoteArgs> {
+    let message = message_arg();
+
+    let no_push = long("no-push")
+        .help("create the tag locally but don't push it")
+        .switch();
+
+    let sign = sign_arg();
+
+    let prefix = prefix_arg("promote the latest prerelease with this prefix").optional();
+
+    let aliases = aliases_arg();
+
+    let stable_tag = stable_tag_arg();
+
+    construct!(PromoteArgs {
+        message,
+        no_push,
+        sign,
+        prefix,
+        aliases,
+        stable_tag,
+    })
+}
+
+fn push_args() -> impl Parser<PushArgs> {
+    let all_tags = all_tags_arg();
+
+    let tag = tag_arg("the tag to push").optional();
+
+    construct!(PushArgs { all_tags, tag })
+}
+
+fn release_args() -> impl Parser<ReleaseArgs> {
+    let draft = long("draft").help("create the release as a draft").switch();
+
+    let prerelease = long("prerelease")
+        .help("mark the release as a prerelease, implied for prerelease tags")
+        .switch();
+
+    let prefix = prefix_arg("release the latest tag with this prefix").optional();
+
+    let tag = tag_arg("the tag to release, defaults to the latest tag").optional();
+
+    construct!(ReleaseArgs {
+        draft,
+        prerelease,
+        prefix,
+        tag,
+    })
+}
+
+fn nightly_args() -> impl Parser<NightlyArgs> {
+    let floating = long("floating")
+        .help("move a single `nightly` tag instead of creating nightly-YYYY-MM-DD")
+        .switch();
+
+    let keep = long("keep")
+        .help("only keep this many dated nightly tags, deleting the oldest ones")
+        .argument::<usize>("N")
+        .optional();
+
+    let no_push = long("no-push")
+        .help("create the tag locally but don't push it")
+        .switch();
+
+    let sign = sign_arg();
+
+    construct!(NightlyArgs {
+        floating,
+        keep,
+        no_push,
+        sign,
+    })
+}
+
+fn retag_args() -> impl Parser<RetagArgs> {
+    let commit = long("commit")
+        .help("the commit to move the tag to (defaults to HEAD, or @ for jj)")
+        .argument::<String>("REV")
+        .optional();
+
+    let no_push = long("no-push")
+        .help("move the tag locally but don't push it")
+        .switch();
+
+    let sign = sign_arg();
+
+    let tag = tag_arg("the tag to move");
+
+    construct!(RetagArgs {
+        commit,
+        no_push,
+        sign,
+        tag,
+    })
+}
+
+fn login_args() -> impl Parser<LoginArgs> {
+    let host = long("host")
+        .help("the forge's host, e.g. github.mycorp.com (defaults to the remote's)")
+        .argument::<String>("HOST")
+        .optional();
+
+    let device = long("device")
+        .help("log in to GitHub in the browser instead of pasting a token")
+        .switch();
+
+    let client_id = long("client-id")
+        .help("the GitHub OAuth app to log in with --device (defaults to github_client_id in the config, then nutag's own app on github.com)")
+        .argument::<String>("ID")
+        .optional();
+
+    construct!(LoginArgs {
+        host,
+        device,
+        client_id
+    })
+}
+
+fn sync_args() -> impl Parser<SyncArgs> {
+    let push = long("push")
+        .help("push tags that only exist locally instead of removing them")
+        .switch();
+
+    construct!(SyncArgs { push })
+}
+
+fn undo_args() -> impl Parser<UndoArgs> {
+    let no_push = long("no-push")
+        .help("only delete the local tag, leave the remote alone")
+        .switch();
+
+    construct!(UndoArgs { no_push })
+}
+
+fn verify_args() -> impl Parser<VerifyArgs> {
+    let prefix = prefix_arg("only verify tags with this prefix").optional();
+
+    let tag = tag_arg("the tag to verify, all release tags are verified when omitted").optional();
+
+    construct!(VerifyArgs { prefix, tag })
+}
+
+impl Default for VersionArgs {
+    fn default() -> Self {
+        Self {
+            major: false,
+            minor: false,
+            patch: false,
+            pre: true,
+            pre_id: None,
+            pre_padding: None,
+            timestamp: false,
+            channel: None,
+            prefixes: Vec::new(),
+            reference: None,
+            branch: None,
+            bump: None,
+            set: None,
+            from: None,
+            from_select: false,
+            maintenance: false,
+            build: None,
+            initial_version: None,
+        }
+    }
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let opts = options().run();
+
+    if opts.generate_manpage {
+        let manpage = options().render_manpage(
+            "nutag",
+            bpaf::doc::Section::General,
+            None,
+            None,
+            Some("nutag manual"),
+        );
+        print!("{manpage}");
+        return Ok(());
+    }
+
+    setup_logging(opts.verbose)?;
+    DRY_RUN.store(opts.dry_run, Ordering::Relaxed);
+    OFFLINE.store(opts.offline, Ordering::Relaxed);
+    ASSUME_YES.store(opts.yes, Ordering::Relaxed);
+    if let Some(remote) = opts.api_remote.clone() {
+        let _ = API_REMOTE.set(remote);
+    }
+    if let Some(remote) = opts.remote.clone() {
+        let _ = API_REMOTE.set(remote.clone());
+        let _ = REMOTE.set(remote);
+    }
+    if let Some(forge) = opts.forge {
+        let _ = FORGE.set(forge);
+    }
+    if let Some(url) = &opts.proxy {
+        let proxy =
+            ureq::Proxy::new(url).with_context(|| format!("--proxy {url} is not a proxy"))?;
+        let _ = PROXY.set(proxy);
+    }
+    if let Some(path) = opts.token_file.clone() {
+        let _ = TOKEN_FILE.set(path);
+    }
+    if let Some(backend) = opts.backend {
+        check_backend(
+            backend,
+            opts.git_dir.is_some(),
+            std::env::var_os("GIT_DIR").is_some(),
+        )?;
+        let _ = BACKEND.set(backend);
+    }
+    // Every git nutag runs picks it up, just like `git --git-dir`
+    if let Some(dir) = &opts.git_dir {
+        std::env::set_var("GIT_DIR", dir);
+    }
+    if let Some(path) = std::env::var_os("SSL_CERT_FILE") {
+        let _ = CA_BUNDLE.set(forge::ca_certificates(std::path::Path::new(&path))?);
+    }
+
+    if offline() {
+        let needs_the_remote = match &opts.cmd {
+            Cmd::Push(_) => Some("push"),
+            Cmd::Release(_) => Some("release"),
+            Cmd::SelfUpdate(_) => Some("self-update"),
+            Cmd::Status => Some("status"),
+            Cmd::Sync(_) => Some("sync"),
+            _ => None,
+        };
+        if let Some(cmd) = needs_the_remote {
+            bail!("nutag {cmd} needs the remote and doesn't work with --offline");
+        }
+    }
+
+    // `init` is how a broken configuration gets fixed, so it must not depend on it
+    match opts.cmd {
+        Cmd::Init => return init(),
+        Cmd::Completions(shell) => return completions(shell),
+        Cmd::SelfUpdate(args) => return self_update::run(version(), args.check),
+        _ => {}
+    }
+    // Everything but logging in needs the repository, so a broken one is reported up front
+    let root = match &opts.cmd {
+        Cmd::Auth(_) => repo_root().ok(),
+        _ => Some(repo_root()?),
+    };
+    let mut config = if bare() {
+        bare_config()?
+    } else {
+        Config::load(root.as_deref())?
+    };
+    if opts.auto_prefix {
+        config.auto_prefix = Some(true);
+    }
+    // Only take effect if there was no --remote or --api-remote
+    if let Some(remote) = config.api_remote.clone() {
+        let _ = API_REMOTE.set(remote);
+    }
+    if let Some(remote) = config.remote.clone() {
+        let _ = API_REMOTE.set(remote.clone());
+        let _ = REMOTE.set(remote);
+    }
+    // Only takes effect if there was no --forge
+    if let Some(forge) = config.forge {
+        let _ = FORGE.set(forge);
+    }
+    if let Some(url) = config.github_api_url.clone() {
+        let _ = GITHUB_API_URL.set(url);
+    }
+    if let Some(command) = config.token_command.clone() {
+        let _ = TOKEN_COMMAND.set(command);
+    }
+    // Only takes effect if there was no SSL_CERT_FILE
+    if let (Some(path), None) = (&config.ca_bundle, CA_BUNDLE.get()) {
+        let _ = CA_BUNDLE.set(forge::ca_certificates(std::path::Path::new(path))?);
+    }
+    if let Some(retries) = config.api_retries {
+        let _ = API_RETRIES.set(retries);
+    }
+    if let (Some(regex), Some(template)) = (&config.tag_regex, &config.tag_template) {
+        let _ = TAG_FORMAT.set(TagFormat::new(regex, template)?);
+    }
+    let _ = SCHEME.set(opts.scheme.or(config.scheme).unwrap_or_default());
+    if scheme() == Scheme::CalVer {
+        let format = config
+            .calver_format
+            .as_deref()
+            .unwrap_or(calver::DEFAULT_FORMAT);
+        let calver = format
+            .parse()
+            .with_context(|| format!("calver_format {format}"))?;
+        let _ = CALVER.set(calver);
+    }
+
+    match opts.cmd {
+        Cmd::Auth(AuthCmd::Login(args)) => login(args, &config),
+        Cmd::Bump(args) => bump(args, &config),
+        Cmd::Changelog(args) => changelog(args, &config),
+        Cmd::Delete(args) => delete(args),
+        Cmd::Describe(args) => describe(args, &config),
+        Cmd::Diff(args) => diff(args, &config),
+        Cmd::Graduate(args) => graduate(args, &config),
+        Cmd::Init | Cmd::Completions(_) | Cmd::SelfUpdate(_) => unreachable!("handled above"),
+        Cmd::Latest(args) => latest(args, &config),
+        Cmd::List(args) => list(args, &config),
+        Cmd::Next(args) => next(args, &config),
+        Cmd::Nightly(args) => nightly(args, &config),
+        Cmd::Promote(args) => promote(args, &config),
+        Cmd::Push(args) => match (args.tag.as_deref(), args.all_tags) {
+            (Some(_), true) => bail!("Either give a TAG or --all-tags, not both"),
+            (None, false) => bail!("Which tag should be pushed? Give a TAG or --all-tags"),
+            (Some(tag), false) => push(&[tag]),
+            (None, true) => push(&[]),
+        },
+        Cmd::Release(args) => release(args, &config),
+        Cmd::Retag(args) => retag(args, &config),
+        Cmd::Show(args) => show(args),
+        Cmd::Status => status(),
+        Cmd::Sync(args) => sync(args),
+        Cmd::Undo(args) => undo(args),
+        Cmd::Verify(args) => verify(args, &config),
+    }
+}
+
+/// The tag nutag suggests next and the commit it should point to
+struct Plan {
+    repo_type: RepoType,
+    /// The tag `next` was derived from, if there were any tags yet
+    latest: Option<Tag>,
+    next: Tag,
+    /// `None` means HEAD
+    commit: Option<String>,
+    /// What each version flag would have made of the latest tag, see [`bump_options`]
+    options: Vec<(&'static str, Tag)>,
+    /// No version flags were given, so `next` is just a patch release by default
+    assumed: bool,
+}
+
+impl Plan {
+    /// The changelog for the commits between the latest tag and the one to be tagged
+    fn changelog(&self, title: &str) -> Result<String, anyhow::Error> {
+        let since = match &self.latest {
+            Some(tag) => Some(git(&["rev-parse", &format!("{tag}^{{commit}}")])?),
+            None => None,
+        };
+        let until = match &self.commit {
+            Some(commit) => commit.clone(),
+            None => git(&["rev-parse", "HEAD"])?,
+        };
+
+        let commits = commits_between(self.repo_type, since.as_deref(), &until)?;
+        let today = date::Date::today().to_string();
+        Ok(changelog::render(title, &today, &commits))
+    }
+}
+
+/// Works out the next tag based on the flags, the current branch and the existing tags.
+/// This has no side effects other than updating the local tags.
+fn plan(
+    args: &mut VersionArgs,
+    prefix: Option<String>,
+    config: &Config,
+) -> Result<Plan, anyhow::Error> {
+    // A release stream of its own, e.g. `lts` for `v1.8.12+lts`
+    let suffixes = config.channel_suffixes.clone().unwrap_or_default();
+    let suffix = args.channel.take_if(|channel| suffixes.contains(channel));
+    if suffix.is_some() && args.build.is_some() {
+        bail!("Can't combine --build with a --channel that is a suffix");
+    }
+    if let Some(channel) = &args.channel {
+        if args.pre_id.is_some() {
+            bail!("Can't combine --channel with --pre-id");
+        }
+        let channels = channels(config);
+        if !channels.contains(channel) {
+            bail!(
+                "Unknown channel {channel}, expected one of {}",
+                channels.join(", ")
+            );
+        }
+        args.pre = true;
+        args.pre_id = Some(format!("{channel}."));
+    }
+    if CALVER.get().is_some() && (args.major || args.minor || args.bump.is_some()) {
+        bail!("With calver the version is today's date, --major, --minor and --bump don't apply");
+    }
+    let versioned = args.major || args.minor || args.pre || args.channel.is_some();
+    if scheme() == Scheme::Build && (versioned || args.bump.is_some() || args.build.is_some()) {
+        bail!("Build numbers only count up, --major, --minor, --pre, --channel, --bump and --build don't apply");
+    }
+    let no_flags = !(args.major || args.minor || args.patch || args.pre || args.set.is_some());
+    let bump = match args.bump {
+        Some(bump) => Some(bump),
+        None if no_flags => config.bump,
+        None => None,
+    };
+    match bump {
+        Some(BumpKind::Major) => args.major = true,
+        Some(BumpKind::Minor) => args.minor = true,
+        Some(BumpKind::Patch) => args.patch = true,
+        Some(BumpKind::Auto) | None => {}
+    }
+    let auto = bump == Some(BumpKind::Auto);
+
+    if [args.major, args.minor, args.patch]
+        .iter()
+        .filter(|v| **v)
+        .count()
+        > 1
+    {
+        bail!("Can't set --major, --minor, --patch together");
+    }
+
+    let mut prefix = resolve_prefix(prefix, config)?;
+    if args.pre_id.is_none() {
+        args.pre_id = config.pre_id.clone();
+    }
+    if args.pre_padding.is_none() {
+        args.pre_padding = config.pre_padding;
+    }
+    args.timestamp |= config.pre_timestamp == Some(true);
+    // A template like `{branch}.` is checked once it is rendered
+    if let Some(pre_id) = args.pre_id.as_ref().filter(|id| !id.contains('{')) {
+        Prerelease::new(&format!("{pre_id}0"))
+            .map_err(|e| anyhow!("Invalid prerelease identifier: {e}"))?;
+    }
+
+    let exact = match &args.set {
+        Some(_) if args.major || args.minor || args.patch || args.pre || auto => {
+            bail!("Can't combine --set with --major, --minor, --patch, --pre or --bump")
+        }
+        Some(_) if args.from.is_some() || args.from_select => {
+            bail!("Can't combine --set with --from or --from-select")
+        }
+        Some(version) => {
+            let tag = exact_tag(version, prefix.as_deref())?;
+            prefix = tag.prefix.clone();
+            Some(tag)
+        }
+        None => None,
+    };
+
+    let repo_type = detect_repo_type()?;
+    debug!("Detected repo type: {:?}", repo_type);
+
+    // Without a checkout there is no branch to be on, so release the configured default
+    // branch, or the one HEAD names otherwise
+    if bare() && args.branch.is_none() && args.reference.is_none() {
+        args.branch = config.default_branch.clone();
+    }
+
+    // A release branch like `release/1.x` only gets tags of its own line
+    let branch = match (&args.branch, &args.reference) {
+        (Some(branch), _) => Some(branch.clone()),
+        (None, None) => current_branch(repo_type)?,
+        (None, Some(_)) => None,
+    };
+    let line = branch.as_deref().and_then(ReleaseLine::from_branch);
+    if let Some(line) = &line {
+        info!("On a release branch, only considering {line} tags");
+        if args.major || (line.minor.is_some() && args.minor) {
+            bail!("Release branches stay within {line}, bump the version on the default branch instead");
+        }
+        // jj would tag trunk() otherwise, `--branch` already points to the branch
+        if let (RepoType::Jj, Some(branch), None) = (repo_type, &branch, &args.branch) {
+            args.reference = Some(branch_head(repo_type, branch)?);
+        }
+    }
+
+    let on_default_branch = match args.branch.clone() {
+        Some(_) if args.reference.is_some() => bail!("Can't combine --branch with --commit"),
+        Some(branch) => {
+            args.reference = Some(branch_head(repo_type, &branch)?);
+            is_default_branch(&branch, config)
+        }
+        None => is_on_default_branch(repo_type, args.reference.as_deref(), config)?,
+    };
+    // Releases are cut from release and maintenance branches too
+    let on_default_branch = on_default_branch || line.is_some() || args.maintenance;
+
+    let mut assumed = false;
+    if args.set.is_none() {
+        if [args.major, args.minor, args.patch, args.pre]
+            .iter()
+            .filter(|v| **v)
+            .count()
+            == 0
+            && !auto
+        {
+            if on_default_branch {
+                info!("No flags given, assuming patch");
+                args.patch = true;
+                assumed = true;
+            } else {
+                info!("No flags given, assuming pretag");
+                args.pre = true;
+            }
+        }
+
+        if on_default_branch && args.pre {
+            error!("Pretags are only allowed on branches");
+            bail!("branch/parameter missmatch");
+        }
+
+        if !on_default_branch && !args.pre {
+            warn!("On branches other than main/master '--pre' is implied");
+            args.pre = true;
+        }
+    }
+
+    if let Some(template) = args
+        .pre_id
+        .as_ref()
+        .filter(|id| args.pre && id.contains('{'))
+    {
+        let branch = match &args.branch {
+            Some(branch) => branch.clone(),
+            None => current_branch(repo_type)?
+                .context("Not on a branch, {branch} in the prerelease identifier needs one")?,
+        };
+        let mut vars = vec![("branch", branch_identifier(&branch))];
+        if template.contains("{ticket}") {
+            vars.push(("ticket", ticket(&branch, config)?));
+        }
+        let pre_id = template::render(template, &vars)?;
+        Prerelease::new(&format!("{pre_id}0"))
+            .map_err(|e| anyhow!("Invalid prerelease identifier {pre_id} from {branch}: {e}"))?;
+        args.pre_id = Some(pre_id);
+    }
+
+    // Get the commit to tag
+    let commit = get_commit_to_tag(repo_type, on_default_branch, args.reference.as_deref())?;
+
+    let mut tags = remote_tags(prefix.as_deref(), config)?;
+    if let Some(line) = &line {
+        tags.retain(|tag| line.contains(&tag.v));
+    }
+    tags.retain(|tag| in_stream(tag, suffix.as_deref(), &suffixes));
+    // Everything else still counts when checking whether the new tag already exists
+    let mut unreachable = Vec::new();
+    if args.maintenance {
+        let reachable = reachable_tag_names(commit.as_deref().unwrap_or("HEAD"))?;
+        (tags, unreachable) = tags
+            .into_iter()
+            .partition(|tag| reachable.contains(&tag.to_string()));
+        match tags.last() {
+            Some(latest) => info!("The latest tag reachable from here is {latest}"),
+            None => warn!("There are no tags reachable from here"),
+        }
+    }
+
+    let from = match (&args.from, args.from_select) {
+        (None, false) => None,
+        (Some(_), true) => bail!("Can't combine --from with --from-select"),
+        (from, _) if CALVER.get().is_some() || scheme() == Scheme::Build => {
+            let flag = if from.is_some() {
+                "--from"
+            } else {
+                "--from-select"
+            };
+            bail!(
+                "{flag} doesn't apply to {}, the next version only depends on the latest",
+                scheme()
+            )
+        }
+        (Some(from), false) => Some(find_from_tag(from, prefix.as_deref(), &tags)?),
+        (None, true) => Some(select_tag(&tags)?),
+    };
+
+    let mut options = Vec::new();
+    let mut next = match (exact, CALVER.get()) {
+        // Whether it already exists is up to `bump`, which can overwrite it with --force
+        (Some(tag), _) => tag,
+        (None, Some(calver)) => calver_tag(calver, tags.last(), prefix.clone(), args)?,
+        (None, None) if scheme() == Scheme::Build => build_number_tag(tags.last(), prefix.clone()),
+        (None, None) => {
+            let year = date::Date::today().year as u64;
+            let initial_version = args
+                .initial_version
+                .clone()
+                .or(config.initial_version.clone());
+            let initial_version = match (scheme(), &line) {
+                (_, Some(line)) => Some(line.first()),
+                (Scheme::Hybrid, None) => {
+                    initial_version.or(Some(semver::Version::new(year, 0, 0)))
+                }
+                _ => initial_version,
+            };
+            let base = from
+                .clone()
+                .or(tags.last().cloned())
+                .unwrap_or_else(|| Tag::initial(prefix.clone(), initial_version));
+            if auto {
+                let since = tags.contains(&base).then_some(&base);
+                let kind = auto_bump(repo_type, since, commit.as_deref())?;
+                info!("The commits since the latest tag call for a {kind} bump");
+                match kind {
+                    // Keep counting up the current prerelease instead of starting another one
+                    _ if args.pre && base.is_prelease() => {}
+                    BumpKind::Major => args.major = true,
+                    BumpKind::Minor => args.minor = true,
+                    // A plain `--pre` already bumps the patch version
+                    BumpKind::Patch | BumpKind::Auto => args.patch = !args.pre,
+                }
+            }
+            if let Some(line) = line.as_ref().filter(|_| args.major || args.minor) {
+                let next = bumped_tag(base.clone(), &mut args.clone(), year);
+                if !line.contains(&next.v) {
+                    bail!("{next} is outside of {line}, bump the version on the default branch instead");
+                }
+            }
+            options = bump_options(&base, args, year);
+            if let Some(line) = &line {
+                options.retain(|(_, option)| line.contains(&option.v));
+            }
+            bumped_tag(base, args, year)
+        }
+    };
+    if let (Some(channel), Some(latest)) = (&args.channel, tags.last()) {
+        let channels = channels(config);
+        let latest_channel = channel_index(&latest.v.pre, &channels);
+        let same_release = latest.release() == next.release();
+        if same_release && latest_channel > channel_index(&next.v.pre, &channels) {
+            bail!("{latest} is already past the {channel} channel");
+        }
+    }
+    let build = args.build.as_ref().or(config.build.as_ref());
+    if let Some(suffix) = &suffix {
+        next.v.build = BuildMetadata::new(suffix)
+            .map_err(|e| anyhow!("The channel {suffix} is not a valid suffix: {e}"))?;
+    } else if let Some(build) = build.filter(|_| scheme() != Scheme::Build) {
+        let commit = match &commit {
+            Some(commit) => commit.clone(),
+            None => git(&["rev-parse", "HEAD"])?,
+        };
+        let run_id = ["GITHUB_RUN_ID", "CI_PIPELINE_ID", "BUILD_NUMBER"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok());
+        if build.contains("{run_id}") && run_id.is_none() {
+            bail!("--build uses {{run_id}}, but none of GITHUB_RUN_ID, CI_PIPELINE_ID or BUILD_NUMBER is set");
+        }
+        let vars = [
+            ("commit_short", git(&["rev-parse", "--short", &commit])?),
+            ("commit", commit),
+            ("date", date::Date::today().to_string()),
+            ("run_id", run_id.unwrap_or_default()),
+        ];
+        next.v.build = build_metadata(build, &vars)?;
+    }
+    // Without a configured style, follow the latest tag
+    next.style = match TAG_FORMAT.get() {
+        Some(_) => TagStyle::Custom,
+        None => config
+            .tag_style
+            .or(tags.last().map(|tag| tag.style))
+            .unwrap_or(match CALVER.get() {
+                Some(_) => TagStyle::Bare,
+                None => TagStyle::V,
+            }),
+    };
+    if unreachable.contains(&next) {
+        bail!("{next} already exists on another branch, pick another version with --set");
+    }
+    if from.is_some() && tags.contains(&next) {
+        bail!("{next} already exists, pick another version with --set");
+    }
+    for (_, option) in &mut options {
+        option.style = next.style;
+        option.v.build = next.v.build.clone();
+    }
+    check_min_version(&next, config)?;
+    // `--set` may go backwards, the changelog starts at the latest tag before it
+    tags.retain(|tag| tag < &next);
+    let latest = tags.pop();
+
+    Ok(Plan {
+        repo_type,
+        latest,
+        next,
+        commit,
+        options,
+        assumed,
+    })
+}
+
+/// Applies the version flags in `args` to `base`, the way the scheme counts
+fn bumped_tag(base: Tag, args: &mut VersionArgs, year: u64) -> Tag {
+    match scheme() {
+        Scheme::Hybrid => hybrid_tag(base, args, year),
+        Scheme::FourPart => four_part_tag(base, args),
+        _ => increment_tag(base, args),
+    }
+}
+
+/// The tag each of `--major`, `--minor`, `--patch` and `--pre` would make of `base`
+fn bump_options(base: &Tag, args: &VersionArgs, year: u64) -> Vec<(&'static str, Tag)> {
+    ["--major", "--minor", "--patch", "--pre"]
+        .into_iter()
+        .map(|flag| {
+            let mut args = VersionArgs {
+                major: flag == "--major",
+                minor: flag == "--minor",
+                patch: flag == "--patch",
+                pre: flag == "--pre",
+                ..args.clone()
+            };
+            (flag, bumped_tag(base.clone(), &mut args, year))
+        })
+        .collect()
+}
+
+/// Whether `tag` belongs to the stream of the suffixed channel `suffix`, or to the main
+/// stream without one. The streams don't mix, `v1.8.12+lts` is no base for `v1.9.0`.
+fn in_stream(tag: &Tag, suffix: Option<&str>, suffixes: &[String]) -> bool {
+    let build = tag.v.build.as_str();
+    match suffix {
+        Some(suffix) => build == suffix,
+        None => !suffixes.iter().any(|suffix| suffix == build),
+    }
+}
+
+/// The prefixes to plan a tag for, `None` being the configured prefix
+fn planned_prefixes(args: &VersionArgs) -> Vec<Option<String>> {
+    if args.prefixes.is_empty() {
+        return vec![None];
+    }
+    args.prefixes.iter().cloned().map(Some).collect()
+}
+
+fn changelog(args: ChangelogArgs, config: &Config) -> Result<(), anyhow::Error> {
+    let mut section = String::new();
+    for prefix in planned_prefixes(&args.version) {
+        let plan = plan(&mut args.version.clone(), prefix, config)?;
+        section.push_str(&plan.changelog(&plan.next.to_string())?);
+    }
+
+    if args.write && dry_run() {
+        info!("Dry run, not updating {}", changelog::FILE_NAME);
+        print!("{section}");
+    } else if args.write && bare() {
+        bail!(
+            "A bare repository has no working tree to write {} to",
+            changelog::FILE_NAME
+        );
+    } else if args.write {
+        changelog::write(&repo_root()?.join(changelog::FILE_NAME), &section)?;
+        info!("Updated {}", changelog::FILE_NAME);
+    } else {
+        print!("{section}");
+    }
+    Ok(())
+}
+
+fn next(args: VersionArgs, config: &Config) -> Result<(), anyhow::Error> {
+    for prefix in planned_prefixes(&args) {
+        let plan = plan(&mut args.clone(), prefix, config)?;
+        println!("{}", plan.next);
+    }
+    Ok(())
+}
+
+fn bump(args: BumpArgs, config: &Config) -> Result<(), anyhow::Error> {
+    if args.changelog && args.message.is_some() {
+        bail!("Can't combine --changelog with --message, --file or --message-template");
+    }
+    if args.lightweight && (args.changelog || args.message.is_some()) {
+        bail!("Lightweight tags have no message");
+    }
+    if args.lightweight && args.sign == Some(true) {
+        bail!("Lightweight tags can't be signed");
+    }
+    if args.force && args.all_tags {
+        bail!("Can't force-push all tags, drop --force or --all-tags");
+    }
+
+    // Every tag is created before pushing, so a failure halfway leaves nothing half-published
+    let mut created = Vec::new();
+    for prefix in planned_prefixes(&args.version) {
+        // Each prefix starts from the flags as given, `plan` fills in the defaults
+        let plan = plan(&mut args.version.clone(), prefix, config)?;
+        if let Some(tag) = create_next_tag(&args, &plan, config)? {
+            created.push(tag);
+        }
+    }
+    if created.is_empty() {
+        return Ok(());
+    }
+    let aliases = Aliases::new(args.aliases, args.stable_tag.as_deref(), config);
+    let aliases = move_aliases(&created, &aliases)?;
+
+    let names: Vec<String> = created.iter().map(Tag::to_string).collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let aliases: Vec<&str> = aliases.iter().map(String::as_str).collect();
+    if args.no_push || offline() {
+        warn!("Not going to push {}", [names, aliases].concat().join(", "));
+        if args.release {
+            warn!("Can't create a release for a tag that wasn't pushed");
+        }
+        return Ok(());
+    }
+
+    if args.force {
+        force_push(&names)?;
+    } else if args.all_tags {
+        push(&[])?;
+    } else {
+        push(&names)?;
+    }
+    if !aliases.is_empty() {
+        force_push(&aliases)?;
+    }
+    if args.release {
+        for tag in &created {
+            let (forge, repo) = release_repo()?;
+            let url = forge.create_release(&repo, &tag.to_string(), false, tag.is_prelease())?;
+            info!("Created release {url}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Prompts for the name of the tag planned in `plan` and creates it locally.
+/// Returns `None` if the user gave up on it.
+fn create_next_tag(
+    args: &BumpArgs,
+    plan: &Plan,
+    config: &Config,
+) -> Result<Option<Tag>, anyhow::Error> {
+    let commit_to_tag = &plan.commit;
+    let prompt_theme = ColorfulTheme::default();
+    let mut next = &plan.next;
+    if !non_interactive() && !plan.options.is_empty() {
+        let from = match &plan.latest {
+            Some(latest) => format!("From {latest}"),
+            None => "Without any tags yet".to_string(),
+        };
+        if plan.assumed {
+            // Rather than silently going with a patch release
+            let items: Vec<String> = plan
+                .options
+                .iter()
+                .map(|(flag, tag)| {
+                    let kind = match *flag {
+                        "--pre" => "prerelease",
+                        flag => flag.trim_start_matches('-'),
+                    };
+                    format!("{kind:<10} {tag}")
+                })
+                .collect();
+            let patch = plan.options.iter().position(|(flag, _)| *flag == "--patch");
+            let picked = Select::with_theme(&prompt_theme)
+                .with_prompt(format!("{from}, which kind of release?"))
+                .items(&items)
+                .default(patch.unwrap_or_default())
+                .interact()?;
+            next = &plan.options[picked].1;
+        } else {
+            println!("{from}:");
+            for (flag, tag) in &plan.options {
+                println!("  {:<8} {tag}", flag.bold());
+            }
+        }
+    }
+    'tag: loop {
+        let t: Tag = if non_interactive() {
+            info!("Not prompting, using {next}");
+            next.clone()
+        } else {
+            Input::with_theme(&prompt_theme)
+                .with_prompt("Next tag")
+                .default(next.to_string())
+                .validate_with(|input: &String| {
+                    Tag::try_from(input.as_str())
+                        .and_then(|tag| check_min_version(&tag, config))
+                        .map_err(|e| e.to_string())
+                })
+                .interact_text()
+                .map_err(|e| anyhow::anyhow!(e))
+                .and_then(Tag::try_from)?
+        };
+
+        // Catch typos like v1.2.7 for v1.2.4 before they are pushed for good. Dates skip
+        // versions all the time, so calver and hybrid versions are left alone.
+        let semver = scheme() == Scheme::SemVer;
+        if let Some(latest) = plan
+            .latest
+            .as_ref()
+            .filter(|l| semver && l.prefix == t.prefix && l.epoch == t.epoch)
+        {
+            let skipped = skipped_versions(&latest.v, &t.v);
+            if !skipped.is_empty() {
+                let mut list: Vec<String> = skipped.iter().take(5).map(|v| v.to_string()).collect();
+                if skipped.len() > 5 {
+                    list.push(format!("{} more", skipped.len() - 5));
+                }
+                warn!("{t} skips {} after {latest}", list.join(", "));
+                if !confirm(&format!("Tag {t} anyway?"), false)? {
+                    continue 'tag;
+                }
+            }
+        }
+
+        let sign = !args.lightweight && args.sign.or(config.sign).unwrap_or(true);
+        let signed = match (args.lightweight, sign) {
+            (true, _) => "lightweight",
+            (false, true) => "signed",
+            (false, false) => "",
+        };
+        info!("Creating {signed} tag {t}");
+
+        let tag_arg = t.to_string();
+        if let Some(old_commit) = tag_commit(&tag_arg) {
+            if !args.force {
+                let e = anyhow!("{tag_arg} already exists, pass --force to replace it");
+                if non_interactive() {
+                    return Err(e);
+                }
+                error!("{e}");
+                let try_again = Confirm::with_theme(&prompt_theme)
+                    .with_prompt("Tag already exists. Try a different one?")
+                    .interact()?;
+                if try_again {
+                    continue 'tag;
+                }
+                return Ok(None);
+            }
+
+            let overwrite = confirm(
+                &format!("{tag_arg} points to {old_commit}. Replace it?"),
+                false,
+            )?;
+            if !overwrite {
+                warn!("Not replacing {tag_arg}");
+                return Ok(None);
+            }
+        }
+
+        let message = match &args.message {
+            Some(Message::Text(message)) => message.clone(),
+            None if args.changelog => plan.changelog(&tag_arg)?,
+            template => {
+                let commit = match commit_to_tag {
+                    Some(commit) => commit.clone(),
+                    None => git(&["rev-parse", "HEAD"])?,
+                };
+                default_message(template.as_ref(), config, &t, &commit, plan.latest.as_ref())?
+            }
+        };
+        let tag_result = NewTag {
+            name: tag_arg.as_str(),
+            message: message.as_str(),
+            commit: commit_to_tag.as_deref(),
+            sign,
+            force: args.force,
+            lightweight: args.lightweight,
+        }
+        .create();
+
+        match tag_result {
+            Ok(_) => {
+                info!("Successfully tagged {t}.");
+                return Ok(Some(t));
+            }
+            Err(e) if non_interactive() => return Err(e),
+            Err(e) if e.to_string().contains("already exists") => {
+                error!("Failed to create tag {e}");
+                let try_again = Confirm::with_theme(&prompt_theme)
+                    .with_prompt("Tag already exists. Try a different one?")
+                    .interact()?;
+
+                if !try_again {
+                    return Ok(None);
+                }
+            }
+            // Asking for another name won't help with e.g. a broken gpg setup
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn delete(args: DeleteArgs) -> Result<(), anyhow::Error> {
+    let tag = args.tag.as_str();
+    let where_ = if args.no_push {
+        "locally"
+    } else {
+        "locally and on the remote"
+    };
+
+    let confirmed = confirm(&format!("Delete tag {tag} {where_}?"), false)?;
+
+    if !confirmed {
+        warn!("Not deleting {tag}");
+        return Ok(());
+    }
+
+    delete_tag(tag, !args.no_push)
+}
+
+/// Deletes `tag` from the local repository and, if `on_remote` is set, from the remote as well.
+fn delete_tag(tag: &str, on_remote: bool) -> Result<(), anyhow::Error> {
+    let refname = format!("refs/tags/{tag}");
+    if git(&["rev-parse", "--quiet", "--verify", refname.as_str()]).is_ok() {
+        git_mut(&["tag", "-d", tag])?;
+        info!("Deleted local tag {tag}");
+    } else {
+        warn!("There is no local tag {tag}");
+    }
+
+    if on_remote && offline() {
+        warn!("Offline, not deleting {tag} on {}", remote());
+    } else if on_remote {
+        git_mut(&["push", remote(), "--delete", refname.as_str()])?;
+        info!("Deleted remote tag {tag}");
+    }
+
+    Ok(())
+}
+
+fn describe(args: DescribeArgs, config: &Config) -> Result<(), anyhow::Error> {
+    let repo_type = detect_repo_type()?;
+    let default_rev = match repo_type {
+        RepoType::Git => "HEAD",
+        RepoType::Jj => "@",
+    };
+    let rev = args.reference.as_deref().unwrap_or(default_rev);
+    let commit = resolve_reference(repo_type, rev)?;
+
+    let prefix = resolve_prefix(args.prefix, config)?;
+    let mut tags: Vec<Tag> = local_tag_names()?
+        .into_iter()
+        .filter_map(|name| Tag::try_from(name).ok())
+        .filter(|tag| tag.prefix == prefix)
+        .collect();
+    tags.sort();
+
+    // The highest version wins, not the closest tag like with `git describe`
+    let Some(tag) = tags.into_iter().rev().find(|tag| {
+        let tagged = format!("{tag}^{{commit}}");
+        git(&[
+            "merge-base",
+            "--is-ancestor",
+            tagged.as_str(),
+            commit.as_str(),
+        ])
+        .is_ok()
+    }) else {
+        bail!("No matching tag is reachable from {rev}");
+    };
+
+    let distance: usize = git(&["rev-list", "--count", &format!("{tag}..{commit}")])?.parse()?;
+    let short_hash = git(&["rev-parse", "--short", commit.as_str()])?;
+    println!("{}", describe_version(&tag, distance, &short_hash));
+    Ok(())
+}
+
+/// Formats a commit `distance` commits after `tag` the way `git describe` does
+fn describe_version(tag: &Tag, distance: usize, short_hash: &str) -> String {
+    if distance == 0 {
+        tag.to_string()
+    } else {
+        format!("{tag}-{distance}-g{short_hash}")
+    }
+}
+
+fn diff(args: DiffArgs, config: &Config) -> Result<(), anyhow::Error> {
+    let repo_type = detect_repo_type()?;
+    let on_default_branch = is_on_default_branch(repo_type, args.reference.as_deref(), config)?;
+    let until = match get_commit_to_tag(repo_type, on_default_branch, args.reference.as_deref())? {
+        Some(commit) => commit,
+        None => git(&["rev-parse", "HEAD"])?,
+    };
+
+    let prefix = resolve_prefix(args.prefix, config)?;
+    let since = match remote_tags(prefix.as_deref(), config)?.pop() {
+        Some(tag) => {
+            info!("Comparing against {tag}");
+            Some(git(&["rev-parse", &format!("{tag}^{{commit}}")])?)
+        }
+        None => {
+            warn!("There are no tags yet, listing all commits");
+            None
+        }
+    };
+
+    for commit in commits_between(repo_type, since.as_deref(), &until)? {
+        println!(
+            "{} {} ({})",
+            commit.short_hash.yellow(),
+            commit.subject(),
+            commit.author
+        );
+    }
+
+    Ok(())
+}
+
+fn completions(shell: Shell) -> Result<(), anyhow::Error> {
+    let style: &'static str = match shell {
+        Shell::Bash => "--bpaf-complete-style-bash",
+        Shell::Zsh => "--bpaf-complete-style-zsh",
+        Shell::Fish => "--bpaf-complete-style-fish",
+        Shell::Elvish => "--bpaf-complete-style-elvish",
+    };
+    // bpaf prints the script and exits when it sees the style flag
+    let _ = options().run_inner(bpaf::Args::from(std::slice::from_ref(&style)).set_name("nutag"));
+    Ok(())
+}
+
+fn init() -> Result<(), anyhow::Error> {
+    if bare() {
+        bail!(
+            "A bare repository has no working tree to write {} to, commit it from a checkout",
+            config::FILE_NAME
+        );
+    }
+    let path = repo_root()?.join(config::FILE_NAME);
+    let theme = ColorfulTheme::default();
+
+    if path.exists() {
+        let overwrite = Confirm::with_theme(&theme)
+            .with_prompt(format!(
+                "{} already exists. Overwrite it?",
+                config::FILE_NAME
+            ))
+            .default(false)
+            .interact()?;
+        if !overwrite {
+            return Ok(());
+        }
+    }
+
+    let prefix: String = Input::with_theme(&theme)
+        .with_prompt("Tag prefix, e.g. `api` for api@v1.0.0 (leave empty for none)")
+        .allow_empty(true)
+        .validate_with(|input: &String| {
+            if input.contains('@') {
+                Err("the prefix can't contain '@'")
+            } else {
+                Ok(())
+            }
+        })
+        .interact_text()?;
+
+    let initial_version: String = Input::with_theme(&theme)
+        .with_prompt("Initial version")
+        .default("0.1.0".to_string())
+        .validate_with(|input: &String| semver::Version::parse(input).map(|_| ()))
+        .interact_text()?;
+
+    let pre_id: String = Input::with_theme(&theme)
+        .with_prompt("Prerelease identifier")
+        .default("pre".to_string())
+        .validate_with(|input: &String| Prerelease::new(input).map(|_| ()))
+        .interact_text()?;
+
+    let default_branch: String = Input::with_theme(&theme)
+        .with_prompt("Default branch")
+        .default(detect_default_branch())
+        .interact_text()?;
+
+    let config = Config {
+        prefix: Some(prefix).filter(|p| !p.is_empty()),
+        initial_version: Some(semver::Version::parse(&initial_version)?),
+        pre_id: Some(pre_id),
+        default_branch: Some(default_branch),
+        ..Default::default()
+    };
+
+    std::fs::write(&path, config.to_toml())?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+/// Guesses the default branch from what the remote considers its HEAD
+fn detect_default_branch() -> String {
+    let remote = remote();
+    git(&[
+        "symbolic-ref",
+        "--short",
+        &format!("refs/remotes/{remote}/HEAD"),
+    ])
+    .ok()
+    .and_then(|head| head.strip_prefix(&format!("{remote}/")).map(str::to_string))
+    .unwrap_or_else(|| "main".to_string())
+}
+
+fn latest(args: LatestArgs, config: &Config) -> Result<(), anyhow::Error> {
+    let prefix = resolve_prefix(args.prefix, config)?;
+    let tags = remote_tags(prefix.as_deref(), config)?;
+
+    let Some(tag) = tags
+        .iter()
+        .rev()
+        .find(|t| !(args.release && t.is_prelease()))
+    else {
+        bail!("No matching tags found");
+    };
+
+    println!("{tag}");
+    Ok(())
+}
+
+fn list(args: ListArgs, config: &Config) -> Result<(), anyhow::Error> {
+    let prefix = resolve_prefix(args.prefix, config)?;
+    let tags = remote_tags(prefix.as_deref(), config)?;
+    let latest_release = tags.iter().rposition(|t| !t.is_prelease());
+
+    let width = tags.iter().map(|t| t.to_string().len()).max().unwrap_or(0);
+    for (idx, tag) in tags.iter().enumerate() {
+        let name = tag.to_string();
+        let mut line = format!("{name:<width$}");
+
+        if args.commits {
+            let commit = git(&["rev-parse", "--short", &format!("{name}^{{commit}}")])?;
+            line.push_str("  ");
+            line.push_str(&commit);
+        }
+
+        if Some(idx) == latest_release {
+            line.push_str(&format!("  {}", "(latest)".green()));
+        } else if tag.is_prelease() {
+            line.push_str(&format!("  {}", "(pre)".dimmed()));
+        }
+
+        println!("{}", line.trim_end());
+    }
+
+    Ok(())
+}
+
+fn release(args: ReleaseArgs, config: &Config) -> Result<(), anyhow::Error> {
+    let tag = match args.tag {
+        Some(tag) => tag,
+        None => {
+            let prefix = resolve_prefix(args.prefix, config)?;
+            let Some(tag) = remote_tags(prefix.as_deref(), config)?.pop() else {
+                bail!("There is no tag to release");
+            };
+            tag.to_string()
+        }
+    };
+
+    let is_prerelease = Tag::try_from(tag.as_str()).is_ok_and(|t| t.is_prelease());
+    let (forge, repo) = release_repo()?;
+    let url = forge.create_release(&repo, &tag, args.draft, args.prerelease || is_prerelease)?;
+    println!("{url}");
+    Ok(())
+}
+
+/// The floating tag of `nutag nightly --floating`
+const NIGHTLY: &str = "nightly";
+
+fn nightly(args: NightlyArgs, config: &Config) -> Result<(), anyhow::Error> {
+    fetch_tags()?;
+    let repo_type = detect_repo_type()?;
+    let trunk = config
+        .default_branch
+        .clone()
+        .unwrap_or_else(detect_default_branch);
+    let commit = branch_head(repo_type, &trunk)?;
+
+    let today = date::Date::today();
+    let name = if args.floating {
+        NIGHTLY.to_string()
+    } else {
+        format!("{NIGHTLY}-{today}")
+    };
+    match tag_commit(&name) {
+        Some(existing) if existing == commit => warn!("{name} already points to {commit}"),
+        Some(existing) if !args.floating => {
+            bail!("{name} already exists on {existing}, `nutag retag {name}` moves it")
+        }
+        _ => tag_nightly(&args, config, &name, &trunk, &commit)?,
+    }
+
+    if let Some(keep) = args.keep.or(config.nightly_keep) {
+        prune_nightlies(keep, !args.no_push)?;
+    }
+    Ok(())
+}
+
+/// Creates (or with `--floating` moves) the nightly tag and pushes it
+fn tag_nightly(
+    args: &NightlyArgs,
+    config: &Config,
+    name: &str,
+    trunk: &str,
+    commit: &str,
+) -> Result<(), anyhow::Error> {
+    let today = date::Date::today();
+    NewTag {
+        name,
+        message: &format!("Nightly build of {trunk} from {today}"),
+        commit: Some(commit),
+        sign: args.sign.or(config.sign).unwrap_or(true),
+        force: args.floating,
+        lightweight: false,
+    }
+    .create()?;
+    info!("Tagged {commit} as {name}");
+
+    if args.no_push {
+        warn!("Not going to push tag");
+    } else if args.floating {
+        force_push(&[name])?;
+    } else {
+        push(&[name])?;
+    }
+    Ok(())
+}
+
+/// Deletes all but the newest `keep` dated nightly tags
+fn prune_nightlies(keep: usize, on_remote: bool) -> Result<(), anyhow::Error> {
+    let mut nightlies: Vec<String> = local_tag_names()?
+        .into_iter()
+        .filter(|name| is_dated_nightly(name))
+        .collect();
+    // The dates sort the same as text
+    nightlies.sort();
+    let old = &nightlies[..nightlies.len().saturating_sub(keep)];
+    if old.is_empty() {
+        return Ok(());
+    }
+
+    let confirmed = confirm(
+        &format!("Delete the old nightly tags {}?", old.join(", ")),
+        true,
+    )?;
+    if !confirmed {
+        warn!("Keeping the old nightly tags");
+        return Ok(());
+    }
+    for tag in old {
+        delete_tag(tag, on_remote)?;
+    }
+    Ok(())
+}
+
+fn is_dated_nightly(name: &str) -> bool {
+    name.strip_prefix(NIGHTLY)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .is_some_and(|date| {
+            date.len() == 10 && date.chars().all(|c| c.is_ascii_digit() || c == '-')
+        })
+}
+
+fn retag(args: RetagArgs, config: &Config) -> Result<(), anyhow::Error> {
+    let tag = args.tag.as_str();
+    let refname = format!("refs/tags/{tag}");
+
+    fetch_tags()?;
+    // The tag object, which is what the remote ref currently points to
+    let old_object = git(&["rev-parse", refname.as_str()])
+        .with_context(|| format!("there is no tag {tag} to move"))?;
+    let old_commit = git(&["rev-parse", &format!("{refname}^{{commit}}")])?;
+
+    let repo_type = detect_repo_type()?;
+    let default_rev = match repo_type {
+        RepoType::Git => "HEAD",
+        RepoType::Jj => "@",
+    };
+    let new_commit = resolve_reference(repo_type, args.commit.as_deref().unwrap_or(default_rev))?;
+
+    if new_commit == old_commit {
+        warn!("{tag} already points to {new_commit}");
+        return Ok(());
+    }
+
+    let confirmed = confirm(
+        &format!("Move {tag} from {old_commit} to {new_commit}?"),
+        false,
+    )?;
+
+    if !confirmed {
+        warn!("Not moving {tag}");
+        return Ok(());
+    }
+
+    // Keep the original annotation around, minus any signature
+    let subject = git(&["tag", "-l", "--format=%(contents:subject)", tag])?;
+    let body = git(&["tag", "-l", "--format=%(contents:body)", tag])?;
+    let message = match (subject.is_empty(), body.is_empty()) {
+        (true, _) => release_message(tag),
+        (false, true) => subject,
+        (false, false) => format!("{subject}\n\n{body}"),
+    };
+
+    NewTag {
+        name: tag,
+        message: message.as_str(),
+        commit: Some(new_commit.as_str()),
+        sign: args.sign.or(config.sign).unwrap_or(true),
+        force: true,
+        lightweight: false,
+    }
+    .create()?;
+    info!("Moved {tag} to {new_commit}");
+
+    if args.no_push || offline() {
+        warn!("Not going to push tag");
+        return Ok(());
+    }
+
+    // Only overwrite the remote tag if it still is what we moved away from
+    let lease = format!("--force-with-lease={refname}:{old_object}");
+    git_mut(&["push", lease.as_str(), remote(), refname.as_str()])?;
+    info!("Done pushing tag");
+
+    Ok(())
+}
+
+fn show(args: ShowArgs) -> Result<(), anyhow::Error> {
+    let tag = args.tag.as_str();
+    let refname = format!("refs/tags/{tag}");
+
+    fetch_tags()?;
+    let kind = git(&["for-each-ref", "--format=%(objecttype)", refname.as_str()])?;
+    if kind.is_empty() {
+        bail!("There is no tag {tag}");
+    }
+
+    let field = |format: &str| git(&["for-each-ref", format, refname.as_str()]);
+    let commit = git(&["rev-parse", &format!("{refname}^{{commit}}")])?;
+    let on_remote = if offline() {
+        "unknown, offline"
+    } else if ls_remote_tags(remote())?.contains(tag) {
+        "yes"
+    } else {
+        "no"
+    };
+
+    println!("{:<8} {tag}", "Tag:".bold());
+    println!("{:<8} {commit}", "Commit:".bold());
+    if kind == "tag" {
+        let tagger = field("--format=%(taggername) %(taggeremail)")?;
+        let date = field("--format=%(taggerdate:iso)")?;
+        println!("{:<8} {tagger}", "Tagger:".bold());
+        println!("{:<8} {date}", "Date:".bold());
+    } else {
+        println!("{:<8} lightweight", "Type:".bold());
+    }
+    println!("{:<8} {on_remote}", "Remote:".bold());
+
+    if kind == "tag" {
+        let subject = field("--format=%(contents:subject)")?;
+        let body = field("--format=%(contents:body)")?;
+        println!();
+        for line in subject.lines().chain(body.lines()) {
+            println!("    {line}");
+        }
+    }
+
+    Ok(())
+}
+
+fn status() -> Result<(), anyhow::Error> {
+    let repo_type = detect_repo_type()?;
+
+    let local = local_tag_names()?;
+    let remote: BTreeSet<String> = remote_tag_names(remote(), None, None)?
+        .into_iter()
+        .collect();
+
+    let print_section = |title: &str, tags: Vec<&String>| {
+        if tags.is_empty() {
+            println!("{title}: none");
+        } else {
+            println!("{title}:");
+            for tag in tags {
+                println!("  {tag}");
+            }
+        }
+    };
+    print_section("Only local", local.difference(&remote).collect());
+    print_section("Only on the remote", remote.difference(&local).collect());
+
+    let (rev, head) = match repo_type {
+        RepoType::Git => ("HEAD", git(&["rev-parse", "HEAD"])?),
+        RepoType::Jj => ("@", resolve_reference(repo_type, "@")?),
+    };
+    let tagged = git(&["tag", "--points-at", head.as_str()])?;
+    if tagged.is_empty() {
+        println!("{rev} is not tagged");
+    } else {
+        println!(
+            "{rev} is tagged as {}",
+            tagged.lines().collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+fn sync(args: SyncArgs) -> Result<(), anyhow::Error> {
+    let before = local_tag_names()?;
+    let upstream = ls_remote_tags(remote())?;
+
+    let local_only: Vec<&String> = before.difference(&upstream).collect();
+    let mut prune = true;
+
+    if !local_only.is_empty() {
+        println!("Tags that only exist locally:");
+        for tag in &local_only {
+            println!("  {tag}");
+        }
+
+        if args.push {
+            let confirmed = confirm(&format!("Push these {} tags?", local_only.len()), false)?;
+            if confirmed {
+                let refspecs: Vec<String> = local_only
+                    .iter()
+                    .map(|t| format!("refs/tags/{t}"))
+                    .collect();
+                let mut push_args = vec!["push", remote()];
+                push_args.extend(refspecs.iter().map(String::as_str));
+                git_mut(&push_args)?;
+                info!("Pushed {} tags", local_only.len());
+            }
+            prune = confirmed;
+        } else {
+            prune = confirm(
+                &format!("Remove these {} local tags?", local_only.len()),
+                false,
+            )?;
+        }
+    }
+
+    if prune {
+        git_mut(&["fetch", remote(), "--prune", "--prune-tags", "--tags"])?;
+    } else {
+        warn!("Keeping local-only tags, only fetching new ones");
+        git(&["fetch", remote(), "--tags"])?;
+    }
+
+    let after = local_tag_names()?;
+    for tag in before.difference(&after) {
+        println!("{} {tag}", "-".red());
+    }
+    for tag in after.difference(&before) {
+        println!("{} {tag}", "+".green());
+    }
+
+    Ok(())
+}
+
+fn verify(args: VerifyArgs, config: &Config) -> Result<(), anyhow::Error> {
+    let prefix = resolve_prefix(args.prefix, config)?;
+    let tags: Vec<String> = match args.tag {
+        Some(tag) => vec![tag],
+        None => {
+            let mut tags: Vec<Tag> = local_tag_names()?
+                .into_iter()
+                .filter_map(|name| Tag::try_from(name).ok())
+                .filter(|tag| tag.prefix == prefix)
+                .collect();
+            tags.sort();
+            tags.iter().map(Tag::to_string).collect()
+        }
+    };
+
+    let mut failures = 0;
+    for tag in &tags {
+        match git(&["tag", "-v", tag.as_str()]) {
+            Ok(_) => println!("{} {tag}", "✔".green()),
+            Err(e) => {
+                failures += 1;
+                let reason = signature_problem(&e.to_string());
+                println!("{} {tag} {reason}", "✘".red());
+                debug!("{e}");
+            }
+        }
+    }
+
+    if failures > 0 {
+        bail!("{failures} of {} tags are not properly signed", tags.len());
+    }
+    Ok(())
+}
+
+/// Turns the output of a failed `git tag -v` into a short explanation
+fn signature_problem(stderr: &str) -> &'static str {
+    if stderr.contains("no signature found") {
+        "is not signed"
+    } else if stderr.contains("allowedSignersFile") {
+        "can't be verified without gpg.ssh.allowedSignersFile"
+    } else if stderr.contains("cannot verify a non-tag object") {
+        "is a lightweight tag"
+    } else {
+        "has a bad signature"
+    }
+}
+
+fn local_tag_names() -> Result<BTreeSet<String>, anyhow::Error> {
+    Ok(git(&["tag", "--list"])?
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// The names of all tags on the remote, as seen by git itself
+fn ls_remote_tags(remote: &str) -> Result<BTreeSet<String>, anyhow::Error> {
+    let output = git(&["ls-remote", "--tags", remote])?;
+    Ok(tag_names_in_ls_remote(&output))
+}
+
+fn tag_names_in_ls_remote(output: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once("refs/tags/"))
+        .map(|(_, name)| name)
+        // Annotated tags show up a second time, peeled to the commit
+        .filter(|name| !name.ends_with("^{}"))
+        .map(str::to_string)
+        .collect()
+}
+
+fn undo(args: UndoArgs) -> Result<(), anyhow::Error> {
+    fetch_tags()?;
+
+    let Some(tag) = last_created_tag()? else {
+        bail!("Could not find any tag created by nutag");
+    };
+
+    let confirmed = confirm(
+        &format!("Delete {tag}, the last tag created by nutag?"),
+        false,
+    )?;
+
+    if !confirmed {
+        warn!("Not deleting {tag}");
+        return Ok(());
+    }
+
+    delete_tag(&tag, !args.no_push)?;
+    println!("Removed {tag}");
+    Ok(())
+}
+
+/// The trailer nutag adds to the annotation of every tag it creates, so it can find them again
+const CREATED_BY_TRAILER: &str = "Created-By";
+
+/// The annotation used when no message is given
+fn release_message(tag: &str) -> String {
+    format!("Release {tag}")
+}
+
+/// Renders the template given on the command line or in the config, if any,
+/// otherwise falls back to [`release_message`]
+fn default_message(
+    message: Option<&Message>,
+    config: &Config,
+    tag: &Tag,
+    commit: &str,
+    previous: Option<&Tag>,
+) -> Result<String, anyhow::Error> {
+    let template = match message {
+        Some(Message::Template(template)) => Some(template),
+        _ => config.message_template.as_ref(),
+    };
+    let Some(template) = template else {
+        return Ok(release_message(&tag.to_string()));
+    };
+
+    let range = match previous {
+        Some(previous) => format!("{previous}..{commit}"),
+        None => commit.to_string(),
+    };
+    let vars = [
+        ("tag", tag.to_string()),
+        ("version", tag.v.to_string()),
+        ("prefix", tag.prefix.clone().unwrap_or_default()),
+        ("commit", commit.to_string()),
+        ("commit_short", git(&["rev-parse", "--short", commit])?),
+        ("date", date::Date::today().to_string()),
+        ("author", git(&["log", "-1", "--format=%an", commit])?),
+        (
+            "commit_count",
+            git(&["rev-list", "--count", range.as_str()])?,
+        ),
+    ];
+    template::render(template, &vars)
+}
+
+fn with_trailer(message: &str) -> String {
+    let trailer = format!("{CREATED_BY_TRAILER}: nutag");
+    if message.lines().any(|line| line == trailer) {
+        return message.to_string();
+    }
+    format!("{message}\n\n{trailer}")
+}
+
+/// An annotated tag that is about to be created
+struct NewTag<'a> {
+    name: &'a str,
+    message: &'a str,
+    /// The commit to tag, `None` tags HEAD
+    commit: Option<&'a str>,
+    sign: bool,
+    /// Replace the tag if it already exists
+    force: bool,
+    /// Just a ref, without message, trailer or signature
+    lightweight: bool,
+}
 
-    let no_push = long("no-push")
-        .help("create the tag locally but don't push it")
-        .switch();
+impl NewTag<'_> {
+    fn create(&self) -> Result<(), anyhow::Error> {
+        let format = if self.sign {
+            Some(check_signing_setup()?)
+        } else {
+            None
+        };
 
-    let no_sign = long("no-sign").help("Don't sign the tag").switch();
+        let message = with_trailer(self.message);
+        let mut tagging_args = vec!["tag"];
+        if !self.lightweight {
+            // Keep lines starting with '#', e.g. markdown headings in changelogs
+            tagging_args.extend(["-a", "--cleanup=whitespace"]);
+        }
+        if self.sign {
+            tagging_args.push("-s");
+        }
+        if self.force {
+            tagging_args.push("-f");
+        }
+        if !self.lightweight {
+            tagging_args.extend(["-m", message.as_str()]);
+        }
+        tagging_args.push(self.name);
+        if let Some(commit) = self.commit {
+            tagging_args.push(commit);
+        }
 
-    let prefix = long("prefix")
-        .help("a prefix to use when creating the tag")
-        .argument::<String>("PREFIX")
-        .optional();
+        git_mut(tagging_args.as_slice()).map_err(|e| match format {
+            Some(format) if e.to_string().contains("unable to sign") => e.context(format!(
+                "Signing {} with {format} failed, check user.signingkey or pass --no-sign",
+                self.name
+            )),
+            _ => e,
+        })?;
 
-    let reference = short('r')
-        .long("ref")
-        .help("git reference to tag (e.g. HEAD, @-, commit hash)")
-        .argument::<String>("REF")
-        .optional();
+        // git can't verify ssh signatures without knowing whose keys to trust
+        if format == Some(SigningFormat::Ssh) && git_config("gpg.ssh.allowedSignersFile").is_none()
+        {
+            warn!(
+                "Not verifying the signature of {}, gpg.ssh.allowedSignersFile is not configured",
+                self.name
+            );
+            return Ok(());
+        }
 
-    let version = option_env!("TAG")
-        .and_then(|v| v.strip_prefix("v"))
-        .unwrap_or("unknwon");
+        // Don't push a tag nobody will be able to verify
+        if self.sign && !dry_run() {
+            if let Err(e) = git(&["tag", "-v", self.name]) {
+                bail!(
+                    "{} was created but {}: {e}",
+                    self.name,
+                    signature_problem(&e.to_string())
+                );
+            }
+            debug!("Verified the signature of {}", self.name);
+        }
+        Ok(())
+    }
+}
 
-    construct!(Args {
-        major,
-        minor,
-        patch,
-        pre,
-        verbose,
-        no_push,
-        no_sign,
-        prefix,
-        reference,
-    })
-    .to_options()
-    .descr("Suggest the next version for tagging")
-    .version(version)
+/// How git signs tags, configured with `gpg.format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SigningFormat {
+    OpenPgp,
+    X509,
+    Ssh,
 }
 
-impl Default for Args {
-    fn default() -> Self {
-        Self {
-            major: false,
-            minor: false,
-            patch: false,
-            pre: true,
-            verbose: 0,
-            no_push: false,
-            no_sign: false,
-            prefix: None,
-            reference: None,
+impl SigningFormat {
+    fn from_config(value: Option<&str>) -> Result<Self, anyhow::Error> {
+        match value {
+            None | Some("openpgp") => Ok(SigningFormat::OpenPgp),
+            Some("x509") => Ok(SigningFormat::X509),
+            Some("ssh") => Ok(SigningFormat::Ssh),
+            Some(other) => bail!("Unknown gpg.format {other}, expected openpgp, x509 or ssh"),
         }
     }
 }
 
-fn main() -> Result<(), anyhow::Error> {
-    let mut args = args().run();
+impl Display for SigningFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SigningFormat::OpenPgp => "gpg",
+            SigningFormat::X509 => "x509",
+            SigningFormat::Ssh => "ssh",
+        })
+    }
+}
 
-    setup_logging(args.verbose)?;
+/// Catches an incomplete signing setup before git fails with a less helpful message
+fn check_signing_setup() -> Result<SigningFormat, anyhow::Error> {
+    let format = SigningFormat::from_config(git_config("gpg.format").as_deref())?;
+    debug!("Signing tags with {format}");
 
-    if [args.major, args.minor, args.patch]
-        .iter()
-        .filter(|v| **v)
-        .count()
-        > 1
-    {
-        bail!("Can't set --major, --minor, --patch together");
+    if format == SigningFormat::Ssh {
+        let Some(key) = git_config("user.signingkey") else {
+            bail!(
+                "gpg.format is ssh but user.signingkey is not set, \
+                 point it at your public key, e.g. `git config user.signingkey ~/.ssh/id_ed25519.pub`"
+            );
+        };
+        // The key can also be given literally, either as `key::ssh-...` or just `ssh-...`
+        if !key.starts_with("key::") && !key.starts_with("ssh-") {
+            let path = match key.strip_prefix("~/") {
+                Some(rest) => {
+                    std::path::Path::new(&std::env::var("HOME").unwrap_or_default()).join(rest)
+                }
+                None => std::path::PathBuf::from(&key),
+            };
+            if !path.exists() {
+                bail!("user.signingkey points to {key}, which does not exist");
+            }
+        }
     }
 
+    Ok(format)
+}
+
+/// The value of a git config key, `None` if it isn't set
+fn git_config(key: &str) -> Option<String> {
+    git(&["config", "--get", key])
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+/// Finds the most recently created tag that carries the nutag trailer
+fn last_created_tag() -> Result<Option<String>, anyhow::Error> {
+    let format =
+        format!("--format=%(refname:short) %(contents:trailers:key={CREATED_BY_TRAILER},valueonly,separator=)");
+    let tags = git(&[
+        "for-each-ref",
+        "--sort=-creatordate",
+        format.as_str(),
+        "refs/tags",
+    ])?;
+
+    let tag = tags
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(_, created_by)| created_by.trim() == "nutag")
+        .map(|(tag, _)| tag.to_string());
+
+    Ok(tag)
+}
+
+fn graduate(args: GraduateArgs, config: &Config) -> Result<(), anyhow::Error> {
+    let prefix = resolve_prefix(args.prefix, config)?;
     let repo_type = detect_repo_type()?;
-    debug!("Detected repo type: {:?}", repo_type);
+    let tags = remote_tags(prefix.as_deref(), config)?;
 
-    let on_default_branch = match repo_type {
-        RepoType::Git => {
-            let reference = args.reference.as_deref().unwrap_or("HEAD");
-            let branch_name = git(&["branch", "--contains", reference])?;
-            let branch_name = branch_name
-                .strip_prefix("*")
-                .map(|s| s.trim())
-                .unwrap_or(branch_name.as_str());
-            ["main", "master"].contains(&branch_name)
-        }
-        RepoType::Jj => {
-            // Check if '@' has 'main' bookmark
-            let reference = args.reference.as_deref().unwrap_or("@");
-            let bookmarks = jj(&["log", "-r", reference, "-T", "bookmarks", "--no-graph"])?;
-            debug!("Current bookmarks: {}", bookmarks);
-            is_default_bookmark(&bookmarks)
-        }
+    if let Some(stable) = tags.iter().find(|t| t.v.major > 0 && !t.is_prelease()) {
+        bail!("{stable} is already past 0.x, there is nothing to graduate");
+    }
+    let Some(latest) = tags.last() else {
+        bail!("There are no tags yet, `nutag --set 1.0.0` starts at 1.0.0 right away");
     };
 
-    if [args.major, args.minor, args.patch, args.pre]
+    let head = match repo_type {
+        RepoType::Git => "HEAD",
+        RepoType::Jj => "@",
+    };
+    let commit = resolve_reference(repo_type, head)?;
+    let breaking = commits_between(repo_type, None, &commit)?
         .iter()
-        .filter(|v| **v)
-        .count()
-        == 0
-    {
-        if on_default_branch {
-            info!("No flags given, assuming patch");
-            args.patch = true;
+        .filter(|commit| {
+            changelog::Conventional::parse(&commit.message).is_some_and(|c| c.breaking)
+        })
+        .count();
+    info!("{}", graduation_summary(&tags, breaking));
+
+    let mut next = Tag {
+        prefix,
+        epoch: latest.epoch,
+        v: semver::Version::new(1, 0, 0),
+        revision: latest.revision.map(|_| 0),
+        style: latest.style,
+        name: None,
+    };
+    if args.rc {
+        // Count up from an earlier release candidate for 1.0.0
+        let before = if latest.release() == next {
+            latest.v.pre.clone()
         } else {
-            info!("No flags given, assuming pretag");
-            args.pre = true;
-        }
+            Prerelease::EMPTY
+        };
+        next.v.pre = next_prerelease(&before, "rc.", 0);
     }
+    check_min_version(&next, config)?;
 
-    if on_default_branch && args.pre {
-        error!("Pretags are only allowed on branches");
-        bail!("branch/parameter missmatch");
+    if !confirm(&format!("Tag {next} on {commit}?"), true)? {
+        warn!("Not graduating");
+        return Ok(());
     }
 
-    if !on_default_branch && !args.pre {
-        warn!("On branches other than main/master '--pre' is implied");
-        args.pre = true;
+    let name = next.to_string();
+    let previous = tags.iter().rev().find(|t| !t.is_prelease());
+    let message = match &args.message {
+        Some(Message::Text(message)) => message.clone(),
+        template => default_message(template.as_ref(), config, &next, &commit, previous)?,
+    };
+    NewTag {
+        name: name.as_str(),
+        message: message.as_str(),
+        commit: Some(commit.as_str()),
+        sign: args.sign.or(config.sign).unwrap_or(true),
+        force: false,
+        lightweight: false,
     }
+    .create()?;
+    info!("Successfully tagged {next}.");
 
-    // Get the commit to tag
-    let commit_to_tag = get_commit_to_tag(repo_type, on_default_branch, args.reference.as_deref())?;
+    if args.no_push {
+        warn!("Not going to push tag");
+    } else {
+        push(&[name.as_str()])?;
+    }
+    Ok(())
+}
+
+/// How the project got to 1.0.0, e.g. `12 releases from v0.1.0 to v0.9.2 with 4 breaking changes`
+fn graduation_summary(tags: &[Tag], breaking: usize) -> String {
+    let releases: Vec<&Tag> = tags.iter().filter(|t| !t.is_prelease()).collect();
+    let history = match (releases.first(), releases.last()) {
+        (Some(first), Some(last)) if releases.len() > 1 => {
+            format!("{} releases from {first} to {last}", releases.len())
+        }
+        (Some(only), _) => format!("1 release, {only}"),
+        _ => "no releases yet".to_string(),
+    };
+    let changes = match breaking {
+        1 => "1 breaking change".to_string(),
+        n => format!("{n} breaking changes"),
+    };
+    format!("{history} with {changes} along the way")
+}
+
+fn promote(args: PromoteArgs, config: &Config) -> Result<(), anyhow::Error> {
+    let prefix = resolve_prefix(args.prefix, config)?;
+    let tags = remote_tags(prefix.as_deref(), config)?;
 
-    info!("Updating local tags via git");
-    let _ = git(&["fetch", "--tags"])?;
+    let Some(pre) = tags.iter().rev().find(|t| t.is_prelease()) else {
+        bail!("There is no prerelease to promote");
+    };
+    let release = pre.release();
+
+    if tags.contains(&release) {
+        bail!("{release} already exists, there is nothing to promote");
+    }
 
-    let github_token = std::env::var("GITHUB_TOKEN")
-        .context("missing api tokent ($GITHUB_TOKEN) to talk to github")?;
+    let pre_name = pre.to_string();
+    let commit = git(&["rev-parse", &format!("{pre_name}^{{commit}}")])?;
 
-    let url = git(&["config", "--get", "remote.origin.url"])?;
-    let extract_repo_name = Regex::new(r#"^([^:]+):([^/]+)/([^\.]+)(.git)?$"#).unwrap();
+    // The release is what was tested as the prerelease, anything newer isn't part of it
+    let repo_type = detect_repo_type()?;
+    let head = match repo_type {
+        RepoType::Git => "HEAD",
+        RepoType::Jj => "@",
+    };
+    let landed = commits_between(
+        repo_type,
+        Some(&commit),
+        &resolve_reference(repo_type, head)?,
+    )?;
+    if !landed.is_empty() {
+        warn!(
+            "{} commits landed since {pre_name}, {release} won't include them:",
+            landed.len()
+        );
+        for commit in &landed {
+            warn!("  {} {}", commit.short_hash, commit.subject());
+        }
+        if !confirm(&format!("Promote {pre_name} without them?"), false)? {
+            warn!("Not promoting {pre_name}, tag a new prerelease first");
+            return Ok(());
+        }
+    }
 
-    let Some(caps) = extract_repo_name.captures(&url) else {
-        bail!("Unable to parse repository URL: {}", url);
+    // Promoting is the step after the last channel, skipping one deserves a second thought
+    let channels = channels(config);
+    let confirmed = match channel_index(&pre.v.pre, &channels) {
+        Some(idx) if idx + 1 < channels.len() => confirm(
+            &format!(
+                "{pre_name} hasn't been through {} yet. Promote it to {release} on {commit} anyway?",
+                channels[idx + 1..].join(", ")
+            ),
+            false,
+        )?,
+        _ => confirm(
+            &format!("Promote {pre_name} to {release} on {commit}?"),
+            true,
+        )?,
     };
 
-    let owner = &caps[2];
-    let name = &caps[3];
-    info!("Going to fetch tags for {owner}/{name}");
+    if !confirmed {
+        warn!("Not promoting {pre_name}");
+        return Ok(());
+    }
 
-    #[derive(SerJson)]
-    struct GqlRequest<'a> {
-        query: &'static str,
-        variables: Variables<'a>,
+    let name = release.to_string();
+    let previous = tags
+        .iter()
+        .rev()
+        .find(|t| !t.is_prelease() && *t < &release);
+    let message = match &args.message {
+        Some(Message::Text(message)) => message.clone(),
+        template => default_message(template.as_ref(), config, &release, &commit, previous)?,
+    };
+    NewTag {
+        name: name.as_str(),
+        message: message.as_str(),
+        commit: Some(commit.as_str()),
+        sign: args.sign.or(config.sign).unwrap_or(true),
+        force: false,
+        lightweight: false,
     }
+    .create()?;
+    info!("Successfully tagged {release}.");
+
+    let aliases = Aliases::new(args.aliases, args.stable_tag.as_deref(), config);
+    let aliases = move_aliases(&[release], &aliases)?;
+    let aliases: Vec<&str> = aliases.iter().map(String::as_str).collect();
 
-    #[derive(SerJson)]
-    struct Variables<'a> {
-        owner: &'a str,
-        name: &'a str,
+    if args.no_push {
+        warn!("Not going to push tag");
+    } else {
+        push(&[name.as_str()])?;
+        if !aliases.is_empty() {
+            force_push(&aliases)?;
+        }
     }
 
-    let query = r#"
-          query ($owner: String!, $name: String!, $endCursor: String) {
-            repository(owner: $owner, name: $name) {
-              refs(refPrefix: "refs/tags/", first: 50, after: $endCursor, orderBy:{field: TAG_COMMIT_DATE, direction: DESC }) {
-                 pageInfo {
-                  endCursor
-                  hasNextPage
-                }
-                nodes {
-                  name
-                }
-              }
+    Ok(())
+}
+
+/// The tags that move along with every release
+struct Aliases<'a> {
+    /// `v1` and `v1.4`
+    floating: bool,
+    /// A tag like `stable` for the newest release
+    stable: Option<&'a str>,
+}
+
+impl<'a> Aliases<'a> {
+    fn new(floating: bool, stable: Option<&'a str>, config: &'a Config) -> Self {
+        Self {
+            floating: floating || config.aliases == Some(true),
+            stable: stable.or(config.stable_tag.as_deref()),
+        }
+    }
+}
+
+/// Points the alias tags of the releases in `created` at them, see [`alias_names`].
+/// Returns the names of the aliases that moved.
+fn move_aliases(created: &[Tag], aliases: &Aliases) -> Result<Vec<String>, anyhow::Error> {
+    if !aliases.floating && aliases.stable.is_none() {
+        return Ok(Vec::new());
+    }
+    let known: Vec<Tag> = local_tag_names()?
+        .iter()
+        .filter_map(|name| Tag::try_from(name.as_str()).ok())
+        .collect();
+
+    let mut moved = Vec::new();
+    for tag in created {
+        let name = tag.to_string();
+        for alias in alias_names(tag, &known, aliases) {
+            if tag_commit(&alias).is_some() && tag_commit(&alias) == tag_commit(&name) {
+                continue;
+            }
+            // Lightweight, so the aliases neither need signing nor show up in `nutag undo`
+            NewTag {
+                name: alias.as_str(),
+                message: "",
+                commit: Some(&format!("{name}^{{commit}}")),
+                sign: false,
+                force: true,
+                lightweight: true,
             }
-          }
-        "#;
+            .create()?;
+            info!("Moved {alias} to {name}");
+            moved.push(alias);
+        }
+    }
+    Ok(moved)
+}
 
-    let body = nanoserde::SerJson::serialize_json(&GqlRequest {
-        query,
-        variables: Variables { owner, name },
-    });
+/// The floating `v1` and `v1.4` tags for the release `tag`, the way GitHub Actions are
+/// versioned, and the stable tag. A line that already has a newer release among `known`
+/// keeps its alias.
+fn alias_names(tag: &Tag, known: &[Tag], aliases: &Aliases) -> Vec<String> {
+    if tag.is_prelease() {
+        return Vec::new();
+    }
+    let newest_in = |line: &dyn Fn(&Tag) -> bool| {
+        !known.iter().any(|other| {
+            other.prefix == tag.prefix && !other.is_prelease() && line(other) && other > tag
+        })
+    };
+
+    let prefix = tag
+        .prefix
+        .as_ref()
+        .map(|prefix| format!("{prefix}@"))
+        .unwrap_or_default();
+    let v = match (tag.style, tag.epoch) {
+        (TagStyle::V, 0) => "v".to_string(),
+        (TagStyle::V, epoch) => format!("v{epoch}!"),
+        (_, 0) => String::new(),
+        (_, epoch) => format!("{epoch}!"),
+    };
+    let (major, minor) = (tag.v.major, tag.v.minor);
 
-    debug!("The query is:\n{body}");
+    let mut names = Vec::new();
+    // Custom tag formats and build numbers have no obvious shorter form
+    let floating = aliases.floating && tag.style != TagStyle::Custom && scheme() != Scheme::Build;
+    let same_epoch = |other: &Tag| other.epoch == tag.epoch;
+    if floating && newest_in(&|other| same_epoch(other) && other.v.major == major) {
+        names.push(format!("{prefix}{v}{major}"));
+    }
+    if floating
+        && newest_in(&|other| same_epoch(other) && other.v.major == major && other.v.minor == minor)
+    {
+        names.push(format!("{prefix}{v}{major}.{minor}"));
+    }
+    if let Some(stable) = aliases.stable.filter(|_| newest_in(&|_| true)) {
+        names.push(format!("{prefix}{stable}"));
+    }
+    names
+}
+
+/// Pushes `tags`, replacing them where the remote already has a different one
+fn force_push(tags: &[&str]) -> Result<(), anyhow::Error> {
+    if offline() {
+        warn!("Offline, not pushing {}", tags.join(", "));
+        return Ok(());
+    }
+    let refspecs: Vec<String> = tags.iter().map(|tag| format!("refs/tags/{tag}")).collect();
+    let mut push_args = vec!["push", "--force", remote()];
+    push_args.extend(refspecs.iter().map(String::as_str));
+    git_mut(&push_args)?;
+    info!("Done pushing tags");
+    Ok(())
+}
 
-    info!("Fetching tags...");
-    let mut response = ureq::post("https://api.github.com/graphql")
-        .header("Accept", "application/vnd.github+json")
-        .header("Authorization", &format!("Bearer {github_token}"))
-        .header("X-GitHub-Api-Version", "2022-11-28")
-        .send(body.as_bytes())?;
+/// The commit `tag` points to, if it exists locally
+fn tag_commit(tag: &str) -> Option<String> {
+    git(&[
+        "rev-parse",
+        "--quiet",
+        "--verify",
+        &format!("refs/tags/{tag}^{{commit}}"),
+    ])
+    .ok()
+}
 
-    if response.status() != 200 {
-        error!("Failed to get tags from github: {response:?}",);
+/// Pushes just `tags` in one go, or all local tags if there are none.
+/// Pushing every tag can resurrect tags deleted upstream, so that is opt-in.
+fn push(tags: &[&str]) -> Result<(), anyhow::Error> {
+    if offline() {
+        warn!("Offline, not pushing {}", tags.join(", "));
         return Ok(());
     }
-    let body = response.body_mut().read_to_string()?;
+    if tags.is_empty() {
+        git_mut(&["push", remote(), "--tags"])?;
+    } else {
+        let refspecs: Vec<String> = tags.iter().map(|tag| format!("refs/tags/{tag}")).collect();
+        let mut push_args = vec!["push", remote()];
+        push_args.extend(refspecs.iter().map(String::as_str));
+        git_mut(&push_args)?;
+    }
+    info!("Done pushing tags");
+    Ok(())
+}
 
-    let gql: Graphql =
-        nanoserde::DeJson::deserialize_json(&body).context("to extract ref data from response")?;
+/// Fetches the tags known to the remote, together with the local ones, keeping only the ones
+/// that parse as a [`Tag`] with the given prefix. The result is sorted, so the latest tag is
+/// the last one.
+fn remote_tags(prefix: Option<&str>, config: &Config) -> Result<Vec<Tag>, anyhow::Error> {
+    let lenient = config.lenient_tags == Some(true);
+    let parse = |name: &str| {
+        if lenient {
+            Tag::parse_lenient(name).ok()
+        } else {
+            Tag::try_from(name).ok()
+        }
+    };
+    let names: Vec<String> = if offline() {
+        warn!(
+            "Offline, only local tags are considered, tags that only exist on {} are missing",
+            remote()
+        );
+        match detect_repo_type()? {
+            RepoType::Git => local_tag_names()?.into_iter().collect(),
+            RepoType::Jj => jj(&["tag", "list", "-T", r#"name ++ "\n""#])?
+                .lines()
+                .map(str::to_string)
+                .collect(),
+        }
+    } else {
+        info!("Updating local tags via git");
+        fetch_tags()?;
+        // Custom tag formats put the prefix elsewhere
+        let starts_with = match TAG_FORMAT.get() {
+            None => prefix.map(|prefix| format!("{prefix}@")),
+            Some(_) => None,
+        };
+        // The newest release is enough to go by, the fetched tags fill in the older ones
+        let version = |name: &str| {
+            parse(name)
+                .filter(|tag| tag.prefix.as_deref() == prefix)
+                .map(|tag| tag.v)
+        };
+        let mut names: BTreeSet<String> =
+            remote_tag_names(api_remote(), starts_with.as_deref(), Some(&version))?
+                .into_iter()
+                .collect();
+        // A tag pushed moments ago may not show up in the forge's API yet
+        let local_only: Vec<String> = local_tag_names()?.difference(&names).cloned().collect();
+        if !local_only.is_empty() {
+            debug!("Also considering the local tags {}", local_only.join(", "));
+        }
+        names.extend(local_only);
+        names.into_iter().collect()
+    };
 
     info!(
         "Going to check for {n} tags for compatibility",
-        n = gql.data.repository.refs.nodes.len()
+        n = names.len()
     );
 
-    let mut tags: Vec<_> = gql
-        .data
-        .repository
-        .refs
-        .nodes
+    let mut tags: Vec<_> = names
         .into_iter()
-        .filter_map(|name| Tag::try_from(name.name).ok())
-        .filter(|tag| tag.prefix == args.prefix)
+        .filter_map(|name| parse(&name))
+        .filter(|tag| tag.prefix.as_deref() == prefix)
         .collect();
 
     tags.sort();
@@ -265,65 +3438,7 @@ fn main() -> Result<(), anyhow::Error> {
             .join(",\n")
     );
 
-    let latest_tag: Tag = tags.pop().unwrap_or(Tag::initial());
-    let next = increment_tag(latest_tag, &args);
-    let prompt_theme = ColorfulTheme::default();
-    'tag: loop {
-        let t: Tag = Input::with_theme(&prompt_theme)
-            .with_prompt("Next tag")
-            .default(next.to_string())
-            .validate_with(|input: &String| Tag::try_from(input.as_str()).map(|_| ()))
-            .interact_text()
-            .map_err(|e| anyhow::anyhow!(e))
-            .and_then(Tag::try_from)?;
-
-        let signed = if args.no_sign { "" } else { "signed" };
-        info!("Creating {signed} tag {t}");
-
-        let tag_arg = t.to_string();
-        let mut tagging_args = vec!["tag", "-a", "-s", "-m", "test", tag_arg.as_str()]
-            .into_iter()
-            // Drop the sining arg if needed
-            .filter(|&arg| if arg == "-s" { !args.no_sign } else { true })
-            .collect::<Vec<_>>();
-
-        let tag_result = if let Some(ref commit) = commit_to_tag {
-            // Tag the specific commit (either from -r flag or jj repo default)
-            tagging_args.push(commit.as_str());
-            git(tagging_args.as_slice())
-        } else {
-            // Tag HEAD (default for git repos without -r flag)
-            git(tagging_args.as_slice())
-        };
-
-        match tag_result {
-            Ok(_) => {
-                info!("Successfully tagged {t}.");
-
-                if args.no_push {
-                    warn!("Not going to push tag");
-                } else {
-                    git(&["push", "--tags"])?;
-                    info!("Done pushing tag");
-                }
-                break 'tag;
-            }
-            Err(e) => {
-                error!("Failed to create tag {e}");
-                if e.to_string().contains("already exists") {
-                    let try_again = Confirm::with_theme(&prompt_theme)
-                        .with_prompt("Tag already exists. Try a different one?")
-                        .interact()?;
-
-                    if !try_again {
-                        break 'tag;
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(())
+    Ok(tags)
 }
 
 fn setup_logging(verbosity: usize) -> Result<(), anyhow::Error> {
@@ -354,46 +3469,298 @@ fn setup_logging(verbosity: usize) -> Result<(), anyhow::Error> {
     };
     base_config.chain(std::io::stderr()).apply()?;
 
-    Ok(())
+    Ok(())
+}
+
+/// The repository the URL of `remote` points to, and the forge hosting it, if nutag knows it
+fn remote_repo(remote: &str) -> Result<Option<(Forge, forge::Repo)>, anyhow::Error> {
+    let url = git(&["config", "--get", &format!("remote.{remote}.url")])?;
+    let configured = FORGE.get().copied();
+    let repo = match forge::Repo::from_url(&url) {
+        Ok(repo) => repo,
+        // e.g. a path to a repository on the same machine
+        Err(e) if configured.is_none() => {
+            debug!("{e}");
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
+    let Some(forge) = Forge::detect(configured, &repo) else {
+        debug!("{} is not a known forge", repo.host);
+        return Ok(None);
+    };
+    debug!("{} on {} is hosted on {forge}", repo.path, repo.host);
+    Ok(Some((forge, repo)))
+}
+
+/// Stores the token for the remote's forge in the keychain, see [`keychain`]
+fn login(args: LoginArgs, config: &Config) -> Result<(), anyhow::Error> {
+    let (forge, host) = match args.host {
+        Some(host) => {
+            let repo = forge::Repo {
+                host,
+                path: String::new(),
+            };
+            (Forge::detect(FORGE.get().copied(), &repo), repo.host)
+        }
+        None => {
+            let (forge, repo) = release_repo()?;
+            (Some(forge), repo.host)
+        }
+    };
+    if forge == Some(Forge::Bitbucket) {
+        bail!("Bitbucket needs BITBUCKET_USERNAME and BITBUCKET_APP_PASSWORD, there is no single token to store");
+    }
+
+    let token = if args.device {
+        // nutag's own app is registered on github.com, not on Enterprise Servers
+        let client_id = args
+            .client_id
+            .or(config.github_client_id.clone())
+            .or_else(|| {
+                GITHUB_CLIENT_ID
+                    .filter(|id| !id.is_empty() && host == "github.com")
+                    .map(str::to_string)
+            });
+        let Some(client_id) = client_id else {
+            bail!("The device flow on {host} needs the client id of a GitHub OAuth app that has it enabled, pass --client-id or set github_client_id in {}", config::FILE_NAME);
+        };
+        if dry_run() {
+            println!("Would log in to {host} and store the token in the keychain");
+            return Ok(());
+        }
+        forge
+            .unwrap_or(Forge::GitHub)
+            .device_login(&host, &client_id)?
+    } else if std::io::stdin().is_terminal() {
+        Password::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("Token for {host}"))
+            .interact()?
+    } else {
+        let mut token = String::new();
+        std::io::stdin().read_line(&mut token)?;
+        token
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        bail!("There is no token to store");
+    }
+
+    if dry_run() {
+        println!("Would store the token for {host} in the keychain");
+        return Ok(());
+    }
+    keychain::store(&host, token)?;
+    println!("Stored the token for {host} in the keychain");
+    Ok(())
+}
+
+/// Releases need the forge's API, there is no way around it. They go where the tags were pushed.
+fn release_repo() -> Result<(Forge, forge::Repo), anyhow::Error> {
+    remote_repo(remote())?.with_context(|| {
+        format!(
+            "Can't tell which forge hosts {}, pick one with --forge",
+            remote()
+        )
+    })
+}
+
+/// The names of the tags on the remote. They come from the forge's API, or from
+/// `git ls-remote` if the forge is unknown or there is no token for it. `starts_with` and
+/// `version` are hints for the server, see [`Forge::tag_names`], so the result may hold
+/// other tags and miss old ones.
+fn remote_tag_names(
+    remote: &str,
+    starts_with: Option<&str>,
+    version: Option<forge::TagVersion>,
+) -> Result<Vec<String>, anyhow::Error> {
+    let Some((forge, repo)) = remote_repo(remote)? else {
+        info!("Listing the tags of {remote} with git ls-remote");
+        return Ok(ls_remote_tags(remote)?.into_iter().collect());
+    };
+    match forge.tag_names(&repo, starts_with, version) {
+        Err(e) if e.downcast_ref::<forge::MissingToken>().is_some() => {
+            info!("{e}, listing the tags with git ls-remote instead");
+            Ok(ls_remote_tags(remote)?.into_iter().collect())
+        }
+        names => names,
+    }
+}
+
+fn git(args: &[&str]) -> Result<String, anyhow::Error> {
+    log::debug!("Running 'git {}'", args.join(" "));
+    let output = Command::new("git").args(args).output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let args = args.join(" ");
+        anyhow::bail!(format!("git {args} failed: {stderr}"));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(stdout)
+}
+
+/// Set by `--remote` or the config
+static REMOTE: OnceLock<String> = OnceLock::new();
+
+/// Set by `--api-remote`, `--remote` or the config
+static API_REMOTE: OnceLock<String> = OnceLock::new();
+
+/// Set by `--forge` or the config
+static FORGE: OnceLock<Forge> = OnceLock::new();
+
+/// Set from `github_api_url` in the config
+static GITHUB_API_URL: OnceLock<String> = OnceLock::new();
+
+fn github_api_url() -> Option<&'static str> {
+    GITHUB_API_URL.get().map(String::as_str)
+}
+
+/// Set by `--proxy`, without it ureq picks the proxy up from the environment
+static PROXY: OnceLock<ureq::Proxy> = OnceLock::new();
+
+/// Read from `SSL_CERT_FILE` or `ca_bundle` in the config, the bundled roots are used otherwise
+static CA_BUNDLE: OnceLock<Vec<ureq::tls::Certificate<'static>>> = OnceLock::new();
+
+/// Set by `--token-file`
+static TOKEN_FILE: OnceLock<String> = OnceLock::new();
+
+/// Set from `token_command` in the config
+static TOKEN_COMMAND: OnceLock<String> = OnceLock::new();
+
+/// Set from `api_retries` in the config
+static API_RETRIES: OnceLock<usize> = OnceLock::new();
+
+/// How often a rate-limited API call is tried again
+fn api_retries() -> usize {
+    API_RETRIES.get().copied().unwrap_or(3)
+}
+
+/// Set from `tag_regex` and `tag_template` in the config
+static TAG_FORMAT: OnceLock<TagFormat> = OnceLock::new();
+
+/// Set by `--scheme` or the config
+static SCHEME: OnceLock<Scheme> = OnceLock::new();
+
+fn scheme() -> Scheme {
+    SCHEME.get().copied().unwrap_or_default()
+}
+
+/// Set with `--scheme calver`, or `scheme = "calver"` in the config
+static CALVER: OnceLock<CalVer> = OnceLock::new();
+
+/// The remote tags are fetched from and pushed to
+fn remote() -> &'static str {
+    REMOTE.get().map(String::as_str).unwrap_or("origin")
+}
+
+/// The remote whose repository on the forge nutag lists the tags of. In a fork that is
+/// `upstream`, where the project's releases are tagged, unless a remote was picked.
+fn api_remote() -> &'static str {
+    static DETECTED: OnceLock<String> = OnceLock::new();
+    if let Some(remote) = API_REMOTE.get() {
+        return remote;
+    }
+    DETECTED.get_or_init(|| {
+        let remotes = git(&["remote"]).unwrap_or_default();
+        preferred_remote(&remotes, remote()).to_string()
+    })
 }
 
-#[derive(Debug, DeJson)]
-struct Graphql {
-    data: Data,
+/// `upstream` if it is among the `remotes` that `git remote` listed, or else `default`
+fn preferred_remote<'a>(remotes: &str, default: &'a str) -> &'a str {
+    if remotes.lines().any(|remote| remote == "upstream") {
+        debug!("Listing the tags of upstream");
+        "upstream"
+    } else {
+        default
+    }
 }
 
-#[derive(Debug, DeJson)]
-struct Data {
-    repository: Repository,
+/// Set by `--yes`, see [`confirm`]
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// Prompts are skipped with `--yes`, or when there is no terminal to answer them, e.g. in CI
+fn non_interactive() -> bool {
+    ASSUME_YES.load(Ordering::Relaxed) || !std::io::stdin().is_terminal()
 }
 
-#[derive(Debug, DeJson)]
-struct Repository {
-    refs: Refs,
+/// Asks a yes/no question. `--yes` answers it, without a terminal nutag refuses to guess.
+fn confirm(prompt: &str, default: bool) -> Result<bool, anyhow::Error> {
+    if ASSUME_YES.load(Ordering::Relaxed) {
+        info!("{prompt} yes (--yes)");
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        bail!("{prompt} There is no terminal to ask, pass --yes to confirm");
+    }
+
+    Ok(Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(default)
+        .interact()?)
 }
 
-#[derive(Debug, DeJson)]
-struct Refs {
-    nodes: Vec<Name>,
+/// Set by `--dry-run`, see [`git_mut`]
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
 }
 
-#[derive(Debug, DeJson)]
-struct Name {
-    name: String,
+/// Set by `--backend`
+static BACKEND: OnceLock<Backend> = OnceLock::new();
+
+fn backend() -> Backend {
+    BACKEND.get().copied().unwrap_or_default()
 }
 
-fn git(args: &[&str]) -> Result<String, anyhow::Error> {
-    log::debug!("Running 'git {}'", args.join(" "));
-    let output = Command::new("git").args(args).output()?;
+/// jj would work on one repository and every git nutag runs on another, the one `--git-dir`
+/// or an inherited `GIT_DIR` point at
+fn check_backend(backend: Backend, git_dir: bool, git_dir_env: bool) -> Result<(), anyhow::Error> {
+    if backend == Backend::Jj && git_dir {
+        bail!("Can't combine --git-dir with --backend jj");
+    }
+    if backend == Backend::Jj && git_dir_env {
+        bail!("GIT_DIR is set, which doesn't work with --backend jj, unset it first");
+    }
+    Ok(())
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        let args = args.join(" ");
-        anyhow::bail!(format!("git {args} failed: {stderr}"));
+/// Set by `--offline`
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+fn offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+fn fetch_tags() -> Result<(), anyhow::Error> {
+    if !offline() {
+        git(&["fetch", remote(), "--tags"])?;
     }
+    Ok(())
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    Ok(stdout)
+/// Runs a git command that changes tags, locally or on the remote.
+/// In a dry run the command is only printed.
+fn git_mut(args: &[&str]) -> Result<String, anyhow::Error> {
+    if dry_run() {
+        let quoted: Vec<String> = args.iter().map(|arg| shell_quote(arg)).collect();
+        println!("git {}", quoted.join(" "));
+        return Ok(String::new());
+    }
+    git(args)
+}
+
+/// Quotes `arg` so the printed command can be pasted into a shell
+fn shell_quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_=./:@^{}+,%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
 }
 
 fn jj(args: &[&str]) -> Result<String, anyhow::Error> {
@@ -410,28 +3777,175 @@ fn jj(args: &[&str]) -> Result<String, anyhow::Error> {
     Ok(stdout)
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum RepoType {
     Git,
     Jj,
 }
 
-fn is_default_bookmark(bookmarks: &str) -> bool {
-    bookmarks.lines().any(|bookmark| bookmark == "main")
+fn is_default_bookmark(bookmarks: &str, default_bookmark: &str) -> bool {
+    bookmarks
+        .lines()
+        .any(|bookmark| bookmark == default_bookmark)
 }
 
-fn detect_repo_type() -> Result<RepoType, anyhow::Error> {
-    // Check for .jj directory
-    if std::path::Path::new(".jj").exists() {
-        return Ok(RepoType::Jj);
+/// The repository nutag runs in
+#[derive(Debug, Clone)]
+struct Repository {
+    /// Where `.nutag.toml` and `CHANGELOG.md` live, the git directory itself when bare
+    root: std::path::PathBuf,
+    repo_type: RepoType,
+    /// There is no working tree, e.g. a mirror on a release server
+    bare: bool,
+}
+
+/// Finds the repository, either the one in `GIT_DIR` (see `--git-dir`) or the one nutag
+/// runs in. Packages in a monorepo live in subdirectories, so look upwards.
+fn find_repository() -> Result<Repository, anyhow::Error> {
+    static FOUND: OnceLock<Repository> = OnceLock::new();
+    if let Some(found) = FOUND.get() {
+        return Ok(found.clone());
+    }
+
+    let backend = backend();
+    if let Some(git_dir) = std::env::var_os("GIT_DIR") {
+        let bare = git(&["rev-parse", "--is-bare-repository"])? == "true";
+        let root = if bare {
+            std::path::PathBuf::from(git_dir)
+        } else {
+            std::path::PathBuf::from(git(&["rev-parse", "--show-toplevel"])?)
+        };
+        let found = Repository {
+            root,
+            repo_type: RepoType::Git,
+            bare,
+        };
+        return Ok(FOUND.get_or_init(|| found).clone());
+    }
+
+    for dir in std::env::current_dir()?.ancestors() {
+        let dot_git = dir.join(".git");
+        let Some((repo_type, bare)) =
+            repository_kind(backend, dir.join(".jj").exists(), dot_git.exists(), || {
+                is_bare_repository(dir)
+            })
+        else {
+            continue;
+        };
+        if repo_type == RepoType::Jj && dot_git.exists() {
+            debug!(
+                "jj and git share {}, using jj (--backend git uses git)",
+                dir.display()
+            );
+        }
+        if repo_type == RepoType::Git && dot_git.is_file() {
+            check_linked_git_dir(&dot_git)?;
+        }
+        if bare {
+            debug!("{} is a bare repository", dir.display());
+        }
+        let found = Repository {
+            root: dir.to_path_buf(),
+            repo_type,
+            bare,
+        };
+        return Ok(FOUND.get_or_init(|| found).clone());
+    }
+
+    match backend {
+        Backend::Auto => bail!("Not in a git or jj repository"),
+        Backend::Git => bail!("Not in a git repository"),
+        Backend::Jj => bail!("Not in a jj repository"),
+    }
+}
+
+/// The kind of repository in a directory with a `.jj` and a `.git` of its own or not, and
+/// whether it is bare. `is_bare` is only asked without either, it runs git.
+fn repository_kind(
+    backend: Backend,
+    jj: bool,
+    dot_git: bool,
+    is_bare: impl FnOnce() -> bool,
+) -> Option<(RepoType, bool)> {
+    let jj = backend != Backend::Git && jj;
+    let git = backend != Backend::Jj;
+    if jj {
+        Some((RepoType::Jj, false))
+    } else if git && dot_git {
+        Some((RepoType::Git, false))
+    } else if git && is_bare() {
+        Some((RepoType::Git, true))
+    } else {
+        None
     }
+}
+
+/// The `.git` directory of a checkout looks the same, but git knows the difference
+fn is_bare_repository(dir: &std::path::Path) -> bool {
+    let looks_like_one =
+        dir.join("HEAD").is_file() && dir.join("objects").is_dir() && dir.join("refs").is_dir();
+    looks_like_one
+        && git(&[
+            "-C",
+            &dir.to_string_lossy(),
+            "rev-parse",
+            "--is-bare-repository",
+        ])
+        .is_ok_and(|bare| bare == "true")
+}
 
-    // Check for .git directory
-    if std::path::Path::new(".git").exists() {
-        return Ok(RepoType::Git);
+/// Whether nutag runs without a working tree, see [`Repository::bare`]
+fn bare() -> bool {
+    find_repository().is_ok_and(|repository| repository.bare)
+}
+
+/// In linked worktrees and submodules `.git` is a file pointing at the git directory
+/// inside the main repository. Git's own error is cryptic when that has been moved.
+fn check_linked_git_dir(dot_git: &std::path::Path) -> Result<(), anyhow::Error> {
+    let contents = std::fs::read_to_string(dot_git)
+        .with_context(|| format!("reading {}", dot_git.display()))?;
+    let Some(git_dir) = linked_git_dir(&contents) else {
+        bail!("{} does not point at a git directory", dot_git.display());
+    };
+    // Submodules point relative to themselves, worktrees with an absolute path
+    let git_dir = dot_git.with_file_name(git_dir);
+    if !git_dir.exists() {
+        bail!(
+            "{} points at {}, which does not exist. If the main repository moved, \
+             `git worktree repair` fixes worktrees and `git submodule absorbgitdirs` submodules",
+            dot_git.display(),
+            git_dir.display()
+        );
     }
+    debug!("Using the git directory {}", git_dir.display());
+    Ok(())
+}
+
+/// The path in a `gitdir: ../.git/modules/lib` line
+fn linked_git_dir(contents: &str) -> Option<&str> {
+    contents
+        .lines()
+        .find_map(|line| line.strip_prefix("gitdir:"))
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+}
+
+fn detect_repo_type() -> Result<RepoType, anyhow::Error> {
+    Ok(find_repository()?.repo_type)
+}
+
+/// Files like `.nutag.toml` and `CHANGELOG.md` live here, wherever nutag is run from
+fn repo_root() -> Result<std::path::PathBuf, anyhow::Error> {
+    Ok(find_repository()?.root)
+}
 
-    bail!("Not in a git or jj repository")
+/// A bare repository has no files, so `.nutag.toml` is read from the tree of HEAD
+fn bare_config() -> Result<Config, anyhow::Error> {
+    let Ok(raw) = git(&["show", &format!("HEAD:{}", config::FILE_NAME)]) else {
+        debug!("No {} in HEAD, using defaults", config::FILE_NAME);
+        return Ok(Config::default());
+    };
+    Config::parse(&raw).with_context(|| format!("parsing {} of HEAD", config::FILE_NAME))
 }
 
 fn get_commit_to_tag(
@@ -442,18 +3956,7 @@ fn get_commit_to_tag(
     // If a reference is explicitly provided, resolve it
     if let Some(git_ref) = reference {
         info!("Using explicit reference: {}", git_ref);
-        let commit_id = match repo_type {
-            RepoType::Git => {
-                // Resolve the git reference to a commit hash
-                git(&["rev-parse", git_ref])?
-            }
-            RepoType::Jj => {
-                // For jj repos, resolve the reference
-                jj(&["log", "-r", git_ref, "-T", "commit_id", "--no-graph"])?
-            }
-        };
-        debug!("Resolved reference {} to commit: {}", git_ref, commit_id);
-        return Ok(Some(commit_id));
+        return resolve_reference(repo_type, git_ref).map(Some);
     }
 
     // Otherwise, use default behavior
@@ -479,64 +3982,668 @@ fn get_commit_to_tag(
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
-struct Tag {
-    prefix: Option<String>,
-    v: semver::Version,
+/// Whether the reference (or HEAD/`@`) is on the branch releases are cut from
+fn is_on_default_branch(
+    repo_type: RepoType,
+    reference: Option<&str>,
+    config: &Config,
+) -> Result<bool, anyhow::Error> {
+    let on_default_branch = match repo_type {
+        RepoType::Git => {
+            let reference = reference.unwrap_or("HEAD");
+            // Older commits, e.g. a merge commit, are usually contained in more than one branch
+            let branches = git(&[
+                "branch",
+                "--contains",
+                reference,
+                "--format=%(refname:short)",
+            ])?;
+            debug!("Branches containing {reference}: {branches}");
+            branches
+                .lines()
+                .any(|branch| is_default_branch(branch, config))
+        }
+        RepoType::Jj => {
+            let default_bookmark = config.default_branch.as_deref().unwrap_or("main");
+            match reference {
+                // An explicit revision only needs to be part of the default bookmark's history
+                Some(reference) => {
+                    let revset = format!("({reference}) & ::{default_bookmark}");
+                    !jj(&[
+                        "log",
+                        "-r",
+                        revset.as_str(),
+                        "-T",
+                        "commit_id",
+                        "--no-graph",
+                    ])?
+                    .is_empty()
+                }
+                None => {
+                    // Check if '@' has 'main' bookmark
+                    let bookmarks = jj(&["log", "-r", "@", "-T", "bookmarks", "--no-graph"])?;
+                    debug!("Current bookmarks: {}", bookmarks);
+                    is_default_bookmark(&bookmarks, default_bookmark)
+                }
+            }
+        }
+    };
+    Ok(on_default_branch)
+}
+
+/// The checked out branch, or for jj the closest bookmark, `None` on a detached HEAD
+fn current_branch(repo_type: RepoType) -> Result<Option<String>, anyhow::Error> {
+    let branch = match repo_type {
+        RepoType::Git => git(&["rev-parse", "--abbrev-ref", "HEAD"])?,
+        RepoType::Jj => jj(&[
+            "log",
+            "-r",
+            "latest(::@ & bookmarks())",
+            "-T",
+            r#"local_bookmarks.map(|b| b.name()).join("\n")"#,
+            "--no-graph",
+        ])?,
+    };
+    let branch = branch.lines().next().unwrap_or_default();
+    Ok(Some(branch.to_string()).filter(|branch| !branch.is_empty() && branch != "HEAD"))
+}
+
+/// The versions a release branch like `release/1.x` or `release/1.4.x` is for
+#[derive(Debug, PartialEq, Eq)]
+struct ReleaseLine {
+    major: u64,
+    /// `None` for a whole major version
+    minor: Option<u64>,
+}
+
+impl ReleaseLine {
+    fn from_branch(branch: &str) -> Option<Self> {
+        let re = Regex::new(r"^release/v?(\d+)(?:\.(\d+))?\.x$").unwrap();
+        let caps = re.captures(branch)?;
+        Some(Self {
+            major: caps.get(1)?.as_str().parse().ok()?,
+            minor: caps.get(2).and_then(|minor| minor.as_str().parse().ok()),
+        })
+    }
+
+    fn contains(&self, v: &semver::Version) -> bool {
+        v.major == self.major && self.minor.is_none_or(|minor| v.minor == minor)
+    }
+
+    /// Where the line starts without any tags yet
+    fn first(&self) -> semver::Version {
+        semver::Version::new(self.major, self.minor.unwrap_or_default(), 0)
+    }
+}
+
+impl Display for ReleaseLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.minor {
+            Some(minor) => write!(f, "{}.{minor}.x", self.major),
+            None => write!(f, "{}.x", self.major),
+        }
+    }
+}
+
+/// The last part of a branch name with everything but letters, digits and dashes replaced,
+/// which is what semver allows in a prerelease, e.g. `fast-parser` for `feature/fast_parser`
+fn branch_identifier(branch: &str) -> String {
+    let name = branch.rsplit('/').next().unwrap_or(branch);
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' => c,
+            _ => '-',
+        })
+        .collect();
+    sanitized
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The ticket a branch like `feature/JIRA-1234-fast-parser` is for, the first group of
+/// `ticket_regex` or the whole match if it has none
+fn ticket(branch: &str, config: &Config) -> Result<String, anyhow::Error> {
+    let pattern = config
+        .ticket_regex
+        .as_deref()
+        .unwrap_or(r"[A-Z][A-Z0-9]+-\d+");
+    let regex = Regex::new(pattern).with_context(|| format!("ticket_regex {pattern}"))?;
+    let captures = regex
+        .captures(branch)
+        .with_context(|| format!("No ticket matching {pattern} in {branch}"))?;
+    let ticket = captures.get(1).or(captures.get(0)).expect("matched");
+    Ok(ticket.as_str().to_string())
+}
+
+/// `default_branch` from the config, or else `main` or `master`
+fn is_default_branch(branch: &str, config: &Config) -> bool {
+    match &config.default_branch {
+        Some(default_branch) => branch == default_branch,
+        None => ["main", "master"].contains(&branch),
+    }
+}
+
+/// The commit at the head of a branch (or jj bookmark), fetched from the remote if it
+/// only exists there
+fn branch_head(repo_type: RepoType, branch: &str) -> Result<String, anyhow::Error> {
+    let remote = remote();
+    let commit = match repo_type {
+        RepoType::Git => {
+            let local = format!("refs/heads/{branch}^{{commit}}");
+            match git(&["rev-parse", "--quiet", "--verify", local.as_str()]) {
+                Ok(commit) => commit,
+                Err(_) if offline() => bail!("There is no local branch {branch}"),
+                Err(_) => {
+                    info!("There is no local branch {branch}, fetching it from {remote}");
+                    git(&["fetch", remote, branch])
+                        .with_context(|| format!("there is no branch {branch}"))?;
+                    git(&[
+                        "rev-parse",
+                        &format!("refs/remotes/{remote}/{branch}^{{commit}}"),
+                    ])?
+                }
+            }
+        }
+        RepoType::Jj => match jj(&["log", "-r", branch, "-T", "commit_id", "--no-graph"]) {
+            Ok(commit) => commit,
+            Err(_) if offline() => bail!("There is no local bookmark {branch}"),
+            Err(_) => {
+                info!("There is no local bookmark {branch}, fetching it from {remote}");
+                jj(&["git", "fetch", "--remote", remote, "--branch", branch])
+                    .with_context(|| format!("there is no bookmark {branch}"))?;
+                let remote_bookmark = format!("{branch}@{remote}");
+                jj(&[
+                    "log",
+                    "-r",
+                    remote_bookmark.as_str(),
+                    "-T",
+                    "commit_id",
+                    "--no-graph",
+                ])?
+            }
+        },
+    };
+    debug!("{branch} points to {commit}");
+    Ok(commit)
+}
+
+/// Resolves a git reference or jj revset to a commit hash
+fn resolve_reference(repo_type: RepoType, reference: &str) -> Result<String, anyhow::Error> {
+    let commit_id = match repo_type {
+        RepoType::Git => {
+            // Resolve the git reference to a commit hash
+            git(&["rev-parse", &format!("{reference}^{{commit}}")])?
+        }
+        RepoType::Jj => {
+            // For jj repos, resolve the reference
+            jj(&["log", "-r", reference, "-T", "commit_id", "--no-graph"])?
+        }
+    };
+    debug!("Resolved reference {} to commit: {}", reference, commit_id);
+    Ok(commit_id)
+}
+
+#[derive(Debug, Clone)]
+struct Commit {
+    short_hash: String,
+    author: String,
+    /// The full commit message (or jj description)
+    message: String,
+}
+
+impl Commit {
+    fn subject(&self) -> &str {
+        self.message.lines().next().unwrap_or_default()
+    }
+}
+
+/// Lists the commits reachable from `until` but not from `since`, newest first.
+/// Without `since` the whole history up to `until` is listed.
+fn commits_between(
+    repo_type: RepoType,
+    since: Option<&str>,
+    until: &str,
+) -> Result<Vec<Commit>, anyhow::Error> {
+    // Fields are separated by the ASCII unit separator, commits by the record separator
+    let output = match repo_type {
+        RepoType::Git => {
+            let range = match since {
+                Some(since) => format!("{since}..{until}"),
+                None => until.to_string(),
+            };
+            git(&["log", "--format=%h%x1f%an%x1f%B%x1e", range.as_str()])?
+        }
+        RepoType::Jj => {
+            let revset = match since {
+                Some(since) => format!("{since}..{until}"),
+                None => format!("::{until}"),
+            };
+            let template = r#"commit_id.short() ++ "\x1f" ++ author.name() ++ "\x1f" ++ description ++ "\x1e""#;
+            jj(&["log", "--no-graph", "-r", revset.as_str(), "-T", template])?
+        }
+    };
+
+    let commits = output
+        .split('\x1e')
+        .map(str::trim)
+        .filter(|record| !record.is_empty())
+        .filter_map(|record| {
+            let mut fields = record.splitn(3, '\x1f');
+            Some(Commit {
+                short_hash: fields.next()?.to_string(),
+                author: fields.next()?.to_string(),
+                message: fields.next().unwrap_or_default().trim().to_string(),
+            })
+        })
+        .collect();
+
+    Ok(commits)
+}
+
+/// What tags look like with the build-number scheme, e.g. `build-1234`
+const BUILD_PREFIX: &str = "build-";
+
+#[derive(Clone, Debug)]
+struct Tag {
+    prefix: Option<String>,
+    /// Starts the versions over, `2!1.4.0` comes after any `1.4.0` or `1!9.0.0`
+    epoch: u64,
+    v: semver::Version,
+    /// The fourth component of `1.2.3.4` with the four-part scheme
+    revision: Option<u64>,
+    style: TagStyle,
+    /// The actual name of an existing tag that was only parsed leniently, e.g. `release-V1.2.3`
+    name: Option<String>,
+}
+
+impl Tag {
+    /// The tag to start from when there are no tags yet
+    fn initial(prefix: Option<String>, version: Option<semver::Version>) -> Self {
+        Self {
+            prefix,
+            epoch: 0,
+            v: version.unwrap_or(semver::Version::new(0, 1, 0)),
+            revision: (scheme() == Scheme::FourPart).then_some(0),
+            style: TagStyle::default(),
+            name: None,
+        }
+    }
+
+    /// Also accepts `V1.2.3`, surrounding whitespace and a leading `release-`, which
+    /// older tags tend to have. The original name is kept, it is what git knows the tag as.
+    fn parse_lenient(name: &str) -> Result<Self, anyhow::Error> {
+        let (prefix, version) = match name.trim().split_once('@') {
+            Some((prefix, version)) => (Some(prefix), version.trim()),
+            None => (None, name.trim()),
+        };
+        let version = match version.get(..8) {
+            Some(marker) if marker.eq_ignore_ascii_case("release-") => &version[8..],
+            _ => version,
+        };
+        let (version, style) = match version.strip_prefix(['v', 'V']) {
+            Some(version) => (version, TagStyle::V),
+            None => (version, TagStyle::Bare),
+        };
+
+        let mut tag = Tag::try_from(match prefix {
+            Some(prefix) => format!("{prefix}@{version}"),
+            None => version.to_string(),
+        })?;
+        tag.style = style;
+        if tag.to_string() != name {
+            tag.name = Some(name.to_string());
+        }
+        Ok(tag)
+    }
+
+    fn is_prelease(&self) -> bool {
+        !self.v.pre.is_empty()
+    }
+
+    /// The same version with the prerelease and build metadata stripped
+    fn release(&self) -> Self {
+        let mut v = self.v.clone();
+        v.pre = Prerelease::EMPTY;
+        v.build = BuildMetadata::EMPTY;
+        Self {
+            prefix: self.prefix.clone(),
+            epoch: self.epoch,
+            v,
+            revision: self.revision,
+            style: self.style,
+            name: None,
+        }
+    }
+}
+
+/// Tags are ordered by prefix, epoch and version. Like semver says, the build metadata doesn't
+/// count, neither does how the name is spelled.
+impl Ord for Tag {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.prefix
+            .cmp(&other.prefix)
+            .then_with(|| self.epoch.cmp(&other.epoch))
+            .then_with(|| {
+                let numbers = |tag: &Tag| (tag.v.major, tag.v.minor, tag.v.patch, tag.revision);
+                numbers(self).cmp(&numbers(other))
+            })
+            .then_with(|| self.v.cmp_precedence(&other.v))
+    }
+}
+
+impl PartialOrd for Tag {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Tag {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Tag {}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(name) = &self.name {
+            return f.write_str(name);
+        }
+        if let (TagStyle::Custom, Some(format)) = (self.style, TAG_FORMAT.get()) {
+            return f.write_str(&format.render(self.prefix.as_deref(), &self.v));
+        }
+        if let Some(prefix) = &self.prefix {
+            f.write_str(prefix)?;
+            f.write_char('@')?;
+        }
+        if scheme() == Scheme::Build {
+            return write!(f, "{BUILD_PREFIX}{}", self.v.major);
+        }
+        if self.style == TagStyle::V {
+            f.write_char('v')?;
+        }
+        if self.epoch > 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+        let v = &self.v;
+        match (CALVER.get(), self.revision) {
+            (Some(calver), _) => f.write_str(&calver.render(v)),
+            (None, Some(revision)) => {
+                write!(f, "{}.{}.{}.{revision}", v.major, v.minor, v.patch)?;
+                if !v.pre.is_empty() {
+                    write!(f, "-{}", v.pre)?;
+                }
+                if !v.build.is_empty() {
+                    write!(f, "+{}", v.build)?;
+                }
+                Ok(())
+            }
+            (None, None) => v.fmt(f),
+        }
+    }
+}
+
+impl TryFrom<&str> for Tag {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.to_string().try_into()
+    }
+}
+
+impl TryFrom<String> for Tag {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if let Some((prefix, v)) = TAG_FORMAT.get().and_then(|format| format.parse(&value)) {
+            return Ok(Tag {
+                prefix,
+                epoch: 0,
+                v,
+                revision: None,
+                style: TagStyle::Custom,
+                name: None,
+            });
+        }
+
+        let (prefix, tag) = if let Some((prefix, tag)) = value.split_once('@') {
+            (Some(prefix.to_string()), tag)
+        } else {
+            (None, value.as_str())
+        };
+
+        if scheme() == Scheme::Build {
+            let number = tag
+                .strip_prefix(BUILD_PREFIX)
+                .and_then(|number| number.parse().ok())
+                .with_context(|| format!("Failed to parse tag {value} as {BUILD_PREFIX}N"))?;
+            return Ok(Tag {
+                prefix,
+                epoch: 0,
+                v: semver::Version::new(number, 0, 0),
+                revision: None,
+                style: TagStyle::Bare,
+                name: None,
+            });
+        }
+
+        let (raw, style) = match tag.strip_prefix("v") {
+            Some(raw) => (raw, TagStyle::V),
+            None => (tag, TagStyle::Bare),
+        };
+        let (epoch, raw) = match raw.split_once('!') {
+            Some((epoch, raw)) => {
+                let epoch = epoch
+                    .parse()
+                    .map_err(|e| anyhow!("Failed to parse the epoch of {value}: {e}"))?;
+                (epoch, raw)
+            }
+            None => (0, raw),
+        };
+        let (v, revision) = match (CALVER.get(), scheme()) {
+            (Some(calver), _) => {
+                let v = calver
+                    .parse(raw)
+                    .with_context(|| format!("Failed to parse tag {value} as calver"))?;
+                (v, None)
+            }
+            (None, Scheme::FourPart) => {
+                let (v, revision) = parse_four_part(raw)
+                    .with_context(|| format!("Failed to parse tag {value} as 1.2.3.4"))?;
+                (v, Some(revision))
+            }
+            (None, _) => {
+                let v = raw
+                    .parse()
+                    .map_err(|e| anyhow!("Failed to parse tag: {e}"))?;
+                (v, None)
+            }
+        };
+
+        Ok(Tag {
+            prefix,
+            epoch,
+            v,
+            revision,
+            style,
+            name: None,
+        })
+    }
+}
+
+/// Picks the bump based on the conventional commits between `latest` and `commit` (or HEAD)
+fn auto_bump(
+    repo_type: RepoType,
+    latest: Option<&Tag>,
+    commit: Option<&str>,
+) -> Result<BumpKind, anyhow::Error> {
+    let since = match latest {
+        Some(tag) => Some(git(&["rev-parse", &format!("{tag}^{{commit}}")])?),
+        None => None,
+    };
+    let until = match commit {
+        Some(commit) => commit.to_string(),
+        None => git(&["rev-parse", "HEAD"])?,
+    };
+
+    let commits = commits_between(repo_type, since.as_deref(), &until)?;
+    Ok(changelog::suggested_bump(&commits))
+}
+
+/// The releases between `latest` and `next` that would never exist, e.g. 1.2.4 to 1.2.6
+/// for 1.2.3 followed by 1.2.7. A prerelease `latest` hasn't been released itself yet.
+fn skipped_versions(latest: &semver::Version, next: &semver::Version) -> Vec<semver::Version> {
+    use semver::Version;
+
+    let unreleased = !latest.pre.is_empty();
+    let latest = Version::new(latest.major, latest.minor, latest.patch);
+    let next = Version::new(next.major, next.minor, next.patch);
+    let mut skipped = Vec::new();
+    if next <= latest {
+        return skipped;
+    }
+    if unreleased {
+        skipped.push(latest.clone());
+    }
+
+    // The minor versions and patches that come before `next` within its major version
+    let line = |first_minor: u64, first_patch: u64| {
+        let minors = (first_minor..next.minor).map(|minor| Version::new(next.major, minor, 0));
+        let first_patch = if next.minor == first_minor {
+            first_patch
+        } else {
+            0
+        };
+        let patches =
+            (first_patch..next.patch).map(|patch| Version::new(next.major, next.minor, patch));
+        minors.chain(patches).collect::<Vec<_>>()
+    };
+    if next.major > latest.major {
+        skipped.extend((latest.major + 1..next.major).map(|major| Version::new(major, 0, 0)));
+        skipped.extend(line(0, 0));
+    } else if next.minor > latest.minor {
+        skipped.extend(line(latest.minor + 1, 0));
+    } else {
+        skipped.extend(line(latest.minor, latest.patch + 1));
+    }
+    skipped
+}
+
+/// Refuses tags below `min_version`, which guards against starting over at 0.1.0 after
+/// the older tags got lost
+fn check_min_version(tag: &Tag, config: &Config) -> Result<(), anyhow::Error> {
+    match &config.min_version {
+        // Starting a new epoch is what resets the versions
+        Some(min) if tag.epoch == 0 && tag.v.cmp_precedence(min).is_lt() => {
+            bail!("{tag} is below min_version {min}, pick a version with --set")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The tag for a version given with `--set`, which may carry its own prefix
+fn exact_tag(version: &str, prefix: Option<&str>) -> Result<Tag, anyhow::Error> {
+    let mut tag = Tag::try_from(version).with_context(|| format!("--set {version}"))?;
+    if tag.prefix.is_none() {
+        tag.prefix = prefix.map(str::to_string);
+    }
+    Ok(tag)
 }
 
-impl Tag {
-    fn initial() -> Self {
-        Self {
-            prefix: None,
-            v: semver::Version::parse("0.1.0").unwrap(),
-        }
-    }
+/// Renders the `--build` template, which has to result in valid semver build metadata
+fn build_metadata(template: &str, vars: &[(&str, String)]) -> Result<BuildMetadata, anyhow::Error> {
+    let build = template::render(template, vars)?;
+    BuildMetadata::new(&build)
+        .map_err(|e| anyhow!("--build {build} is not valid build metadata: {e}"))
+}
 
-    fn is_prelease(&self) -> bool {
-        !self.v.pre.is_empty()
+/// The next tag with calendar versioning, see [`CalVer::next`]
+fn calver_tag(
+    calver: &CalVer,
+    latest: Option<&Tag>,
+    prefix: Option<String>,
+    params: &VersionArgs,
+) -> Result<Tag, anyhow::Error> {
+    let mut v = calver.next(latest.map(|tag| &tag.v), date::Date::today())?;
+    if params.pre {
+        v.pre = match latest {
+            Some(latest) if latest.release().v == v => prerelease_after(&latest.v.pre, params),
+            _ => prerelease_after(&Prerelease::EMPTY, params),
+        };
     }
+    Ok(Tag {
+        prefix,
+        epoch: latest.map_or(0, |tag| tag.epoch),
+        v,
+        revision: None,
+        style: TagStyle::default(),
+        name: None,
+    })
 }
 
-impl Display for Tag {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(prefix) = &self.prefix {
-            f.write_str(prefix)?;
-            f.write_char('@')?;
-        }
-        f.write_char('v')?;
-        self.v.fmt(f)
+/// Like [`increment_tag`], but a major bump snaps to `year`. Within the same year there is
+/// no major version left to bump, so breaking changes bump the minor version.
+fn hybrid_tag(before: Tag, params: &mut VersionArgs, year: u64) -> Tag {
+    if params.major && before.v.major >= year {
+        info!("{before} is already from {year}, bumping the minor version instead");
+        params.major = false;
+        params.minor = true;
+    }
+    let mut next = increment_tag(before, params);
+    if params.major {
+        next.v.major = year;
     }
+    next
 }
 
-impl TryFrom<&str> for Tag {
-    type Error = anyhow::Error;
-
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        value.to_string().try_into()
+/// The build number after `latest`, which is kept in the major version
+fn build_number_tag(latest: Option<&Tag>, prefix: Option<String>) -> Tag {
+    let number = latest.map_or(0, |tag| tag.v.major) + 1;
+    Tag {
+        v: semver::Version::new(number, 0, 0),
+        revision: None,
+        ..Tag::initial(prefix, None)
     }
 }
 
-impl TryFrom<String> for Tag {
-    type Error = anyhow::Error;
-
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        let (prefix, tag) = if let Some((prefix, tag)) = value.split_once('@') {
-            (Some(prefix.to_string()), tag)
-        } else {
-            (None, value.as_str())
-        };
+/// Like [`increment_tag`], but on the last three components of `1.2.3.4`. `--major` bumps the
+/// second one, the first only changes with `--set`.
+fn four_part_tag(before: Tag, params: &VersionArgs) -> Tag {
+    let v = &before.v;
+    let mut shifted = before.clone();
+    shifted.v = semver::Version {
+        major: v.minor,
+        minor: v.patch,
+        patch: before.revision.unwrap_or_default(),
+        pre: v.pre.clone(),
+        build: v.build.clone(),
+    };
+    let bumped = increment_tag(shifted, params);
 
-        let raw = tag.strip_prefix("v").unwrap_or(&value);
-        let v: semver::Version = raw
-            .parse()
-            .map_err(|e| anyhow!("Failed to parse tag: {e}"))?;
+    let mut next = bumped.clone();
+    next.v.major = before.v.major;
+    next.v.minor = bumped.v.major;
+    next.v.patch = bumped.v.minor;
+    next.revision = Some(bumped.v.patch);
+    next
+}
 
-        Ok(Tag { prefix, v })
-    }
+/// Parses `1.2.3.4-pre0`, which isn't semver, into `1.2.3-pre0` and the revision 4
+fn parse_four_part(raw: &str) -> Result<(semver::Version, u64), anyhow::Error> {
+    let end = raw.find(['-', '+']).unwrap_or(raw.len());
+    let Some((numbers, revision)) = raw[..end].rsplit_once('.') else {
+        bail!("expected four numbers");
+    };
+    let revision = revision.parse()?;
+    let v: semver::Version = format!("{numbers}{}", &raw[end..]).parse()?;
+    Ok((v, revision))
 }
 
-fn increment_tag(before: Tag, params: &Args) -> Tag {
+fn increment_tag(before: Tag, params: &VersionArgs) -> Tag {
     let mut next_v = before.v.clone();
     next_v.build = BuildMetadata::from_str("").unwrap();
     if params.major {
@@ -544,7 +4651,7 @@ fn increment_tag(before: Tag, params: &Args) -> Tag {
         next_v.minor = 0;
         next_v.patch = 0;
         next_v.pre = if params.pre {
-            next_prerelease(&before.v.pre)
+            prerelease_after(&before.v.pre, params)
         } else {
             Prerelease::from_str("").unwrap()
         };
@@ -553,7 +4660,7 @@ fn increment_tag(before: Tag, params: &Args) -> Tag {
         next_v.minor += 1;
         next_v.patch = 0;
         next_v.pre = if params.pre {
-            next_prerelease(&before.v.pre)
+            prerelease_after(&before.v.pre, params)
         } else {
             Prerelease::from_str("").unwrap()
         };
@@ -566,38 +4673,629 @@ fn increment_tag(before: Tag, params: &Args) -> Tag {
     }
     if params.pre {
         if before.is_prelease() {
-            next_v.pre = next_prerelease(&before.v.pre);
+            next_v.pre = prerelease_after(&before.v.pre, params);
         } else if !(params.major || params.minor || params.patch) {
             next_v.patch += 1;
-            next_v.pre = Prerelease::from_str("pre0").unwrap();
+            next_v.pre = prerelease_after(&Prerelease::EMPTY, params);
         }
     }
     Tag {
         prefix: before.prefix.clone(),
+        epoch: before.epoch,
         v: next_v,
+        revision: before.revision,
+        style: before.style,
+        name: None,
+    }
+}
+
+/// The prerelease channels from the config, in order
+fn channels(config: &Config) -> Vec<String> {
+    match &config.channels {
+        Some(channels) => channels.clone(),
+        None => ["alpha", "beta", "rc"].map(String::from).to_vec(),
     }
 }
 
-fn next_prerelease(before: &Prerelease) -> Prerelease {
-    let prerelase = before.as_str();
-    let attempt: i32 = prerelase
-        .strip_prefix("pre")
-        .and_then(|raw| raw.parse::<i32>().ok())
-        .map(|n| n + 1)
-        .unwrap_or(0);
+/// Where the channel of a prerelease like `beta.2` comes in `channels`
+fn channel_index(pre: &Prerelease, channels: &[String]) -> Option<usize> {
+    let id = pre.as_str().split('.').next()?;
+    let id = id.trim_end_matches(|c: char| c.is_ascii_digit());
+    channels.iter().position(|channel| channel == id)
+}
+
+/// The prerelease after `before`, counted up or with the current time for `--timestamp`
+fn prerelease_after(before: &Prerelease, params: &VersionArgs) -> Prerelease {
+    let pre_id = params.pre_id.as_deref().unwrap_or("pre");
+    if params.timestamp {
+        let id = pre_id.trim_end_matches('.');
+        return Prerelease::from_str(&format!("{id}.{}", date::timestamp())).unwrap();
+    }
+    next_prerelease(before, pre_id, params.pre_padding.unwrap_or_default())
+}
+
+/// Counts up `{pre_id}N` or `{pre_id}.N`, whichever `before` uses. A new count starts at 0,
+/// dot-separated if `pre_id` ends with a dot, e.g. `rc.` for `rc.0`. Unless it is dot-separated,
+/// the counter is zero-padded to `padding` digits, e.g. `pre007`.
+fn next_prerelease(before: &Prerelease, pre_id: &str, padding: usize) -> Prerelease {
+    let (id, mut separator) = match pre_id.strip_suffix('.') {
+        Some(id) => (id, "."),
+        None => (pre_id, ""),
+    };
+    let mut attempt = 0;
+    if let Some(rest) = before.as_str().strip_prefix(id) {
+        let (before_separator, n) = match rest.strip_prefix('.') {
+            Some(n) => (".", n),
+            None => ("", rest),
+        };
+        if let Ok(n) = n.parse::<u64>() {
+            separator = before_separator;
+            attempt = n + 1;
+        }
+    }
 
-    Prerelease::from_str(&format!("pre{attempt}")).unwrap()
+    // Numeric identifiers can't have leading zeros
+    let padding = match separator {
+        "." => 0,
+        _ => padding,
+    };
+    Prerelease::from_str(&format!("{id}{separator}{attempt:0padding$}")).unwrap()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{increment_tag, Tag};
+    use crate::{increment_tag, linked_git_dir, Tag};
+
+    #[test]
+    fn reads_the_git_dir_of_worktrees_and_submodules() {
+        assert_eq!(
+            linked_git_dir("gitdir: /src/app/.git/worktrees/feature\n"),
+            Some("/src/app/.git/worktrees/feature")
+        );
+        assert_eq!(
+            linked_git_dir("gitdir: ../.git/modules/lib\n"),
+            Some("../.git/modules/lib")
+        );
+        assert_eq!(linked_git_dir("ref: refs/heads/main\n"), None);
+        assert_eq!(linked_git_dir("gitdir:\n"), None);
+    }
 
     #[test]
     fn bookmarks_containing_main_are_not_the_default_branch() {
-        assert!(!crate::is_default_bookmark("new-domain-model"));
-        assert!(!crate::is_default_bookmark("maintain-stuff"));
-        assert!(crate::is_default_bookmark("main"));
+        assert!(!crate::is_default_bookmark("new-domain-model", "main"));
+        assert!(!crate::is_default_bookmark("maintain-stuff", "main"));
+        assert!(crate::is_default_bookmark("main", "main"));
+    }
+
+    #[test]
+    fn describes_commits_after_a_tag() {
+        let tag = Tag::try_from("api@v1.4.2").unwrap();
+
+        assert_eq!(
+            crate::describe_version(&tag, 7, "abc1234"),
+            "api@v1.4.2-7-gabc1234"
+        );
+        assert_eq!(crate::describe_version(&tag, 0, "abc1234"), "api@v1.4.2");
+    }
+
+    #[test]
+    fn plans_a_tag_per_prefix() {
+        use bpaf::Parser;
+        let args = crate::version_args()
+            .to_options()
+            .run_inner(&["--prefix", "api", "--prefix", "web"])
+            .unwrap();
+        assert_eq!(
+            crate::planned_prefixes(&args),
+            [Some("api".to_string()), Some("web".to_string())]
+        );
+
+        assert_eq!(
+            crate::planned_prefixes(&crate::VersionArgs::default()),
+            [None]
+        );
+    }
+
+    #[test]
+    fn quotes_dry_run_arguments_for_the_shell() {
+        assert_eq!(crate::shell_quote("refs/tags/v1.0.0"), "refs/tags/v1.0.0");
+        assert_eq!(
+            crate::shell_quote("it's\n\nCreated-By: nutag"),
+            "'it'\\''s\n\nCreated-By: nutag'"
+        );
+        assert_eq!(crate::shell_quote(""), "''");
+    }
+
+    #[test]
+    fn keeps_the_style_of_the_tag() {
+        for name in ["v1.2.3", "1.2.3", "api@1.2.3-pre0"] {
+            assert_eq!(Tag::try_from(name).unwrap().to_string(), name);
+        }
+    }
+
+    #[test]
+    fn parses_historical_tags_leniently() {
+        let tag = Tag::parse_lenient(" release-V1.2.3").unwrap();
+        assert_eq!(tag.v, semver::Version::new(1, 2, 3));
+        assert_eq!(tag.to_string(), " release-V1.2.3");
+        assert_eq!(tag.release().to_string(), "v1.2.3");
+
+        let tag = Tag::parse_lenient("api@release-2.0.0").unwrap();
+        assert_eq!(tag.prefix.as_deref(), Some("api"));
+        assert_eq!(tag.v, semver::Version::new(2, 0, 0));
+
+        assert!(Tag::try_from("V1.2.3").is_err());
+        assert!(Tag::parse_lenient("release-one").is_err());
+    }
+
+    #[test]
+    fn takes_the_commit_to_tag() {
+        use bpaf::Parser;
+        // `--ref` and `-r` are the original names
+        for argv in [
+            &["--commit", "trunk()-"],
+            &["--ref", "trunk()-"],
+            &["-r", "trunk()-"],
+        ] {
+            let args = crate::version_args().to_options().run_inner(argv).unwrap();
+            assert_eq!(args.reference.as_deref(), Some("trunk()-"), "{argv:?}");
+        }
+    }
+
+    #[test]
+    fn tags_the_head_of_a_branch() {
+        use bpaf::Parser;
+        let args = crate::version_args()
+            .to_options()
+            .run_inner(&["--branch", "release/1.x", "--patch"])
+            .unwrap();
+        assert_eq!(args.branch.as_deref(), Some("release/1.x"));
+
+        let config = crate::Config::default();
+        assert!(crate::is_default_branch("main", &config));
+        assert!(crate::is_default_branch("master", &config));
+        assert!(!crate::is_default_branch("release/1.x", &config));
+
+        let config = crate::Config {
+            default_branch: Some("trunk".into()),
+            ..Default::default()
+        };
+        assert!(crate::is_default_branch("trunk", &config));
+        assert!(!crate::is_default_branch("main", &config));
+    }
+
+    #[test]
+    fn marks_tags_as_created_by_nutag() {
+        let message = crate::with_trailer("Release v1.0.0");
+        assert_eq!(message, "Release v1.0.0\n\nCreated-By: nutag");
+        assert_eq!(crate::with_trailer(&message), message);
+    }
+
+    #[test]
+    fn reads_the_tags_git_ls_remote_lists() {
+        let output = "\
+1b2c3d4e5f60718293a4b5c6d7e8f90123456789\trefs/tags/api@v1.0.0
+0a1b2c3d4e5f60718293a4b5c6d7e8f901234567\trefs/tags/v1.2.0
+9f8e7d6c5b4a39281706f5e4d3c2b1a098765432\trefs/tags/v1.2.0^{}
+";
+        assert_eq!(
+            crate::tag_names_in_ls_remote(output),
+            ["api@v1.0.0".to_string(), "v1.2.0".to_string()].into()
+        );
+        assert!(crate::tag_names_in_ls_remote("").is_empty());
+    }
+
+    #[test]
+    fn explains_failed_signature_checks() {
+        assert_eq!(
+            crate::signature_problem("error: no signature found"),
+            "is not signed"
+        );
+        assert_eq!(
+            crate::signature_problem("error: gpg.ssh.allowedSignersFile needs to be configured"),
+            "can't be verified without gpg.ssh.allowedSignersFile"
+        );
+        assert_eq!(
+            crate::signature_problem(
+                "error: v1.0.0: cannot verify a non-tag object of type commit."
+            ),
+            "is a lightweight tag"
+        );
+        assert_eq!(
+            crate::signature_problem("gpg: BAD signature"),
+            "has a bad signature"
+        );
+    }
+
+    #[test]
+    fn annotates_releases_by_default() {
+        use bpaf::Parser;
+        let tag = Tag::try_from("api@v1.2.0").unwrap();
+        let config = crate::Config::default();
+        assert_eq!(
+            crate::default_message(None, &config, &tag, "abc1234", None).unwrap(),
+            "Release api@v1.2.0"
+        );
+
+        let message = crate::message_arg()
+            .to_options()
+            .run_inner(&["--message", "Hotfix for the login page"])
+            .unwrap();
+        assert!(
+            matches!(message, Some(crate::Message::Text(text)) if text == "Hotfix for the login page")
+        );
+        assert!(crate::message_arg()
+            .to_options()
+            .run_inner(&["--message", "a", "--message-template", "{tag}"])
+            .is_err());
+    }
+
+    #[test]
+    fn sets_an_exact_version() {
+        assert_eq!(
+            crate::exact_tag("2.3.0", Some("api")).unwrap(),
+            Tag::try_from("api@2.3.0").unwrap()
+        );
+        assert_eq!(
+            crate::exact_tag("web@v2.3.0-rc1", Some("api")).unwrap(),
+            Tag::try_from("web@v2.3.0-rc1").unwrap()
+        );
+        assert!(crate::exact_tag("2.3", None).is_err());
+    }
+
+    #[test]
+    fn reads_the_signing_format() {
+        use crate::SigningFormat;
+
+        assert_eq!(
+            SigningFormat::from_config(None).unwrap(),
+            SigningFormat::OpenPgp
+        );
+        assert_eq!(
+            SigningFormat::from_config(Some("ssh")).unwrap(),
+            SigningFormat::Ssh
+        );
+        assert!(SigningFormat::from_config(Some("pgp")).is_err());
+    }
+
+    #[test]
+    fn promoting_strips_the_prerelease() {
+        let pre = Tag::try_from("api@v1.4.0-pre3").unwrap();
+
+        assert_eq!(pre.release(), Tag::try_from("api@v1.4.0").unwrap());
+    }
+
+    #[test]
+    fn snaps_the_major_version_to_the_year() {
+        let mut params = crate::VersionArgs {
+            major: true,
+            pre: false,
+            ..Default::default()
+        };
+        let after = crate::hybrid_tag(Tag::try_from("v2024.3.1").unwrap(), &mut params, 2025);
+        assert_eq!(after, Tag::try_from("v2025.0.0").unwrap());
+
+        let after = crate::hybrid_tag(Tag::try_from("v2025.0.0").unwrap(), &mut params, 2025);
+        assert_eq!(after, Tag::try_from("v2025.1.0").unwrap());
+    }
+
+    #[test]
+    fn ignores_build_metadata_when_comparing() {
+        let build = Tag::try_from("v1.2.3+ci.1234").unwrap();
+        assert_eq!(build, Tag::try_from("v1.2.3+gabc1234").unwrap());
+        assert!(build < Tag::try_from("v1.2.4").unwrap());
+
+        let vars = [("commit_short", "abc1234".to_string())];
+        assert_eq!(
+            crate::build_metadata("g{commit_short}", &vars)
+                .unwrap()
+                .as_str(),
+            "gabc1234"
+        );
+        assert!(crate::build_metadata("{commit_short} !", &vars).is_err());
+    }
+
+    #[test]
+    fn finds_the_channel_of_a_prerelease() {
+        let channels = ["alpha", "beta", "rc"].map(String::from);
+        let channel = |pre: &str| crate::channel_index(&pre.parse().unwrap(), &channels);
+
+        assert_eq!(channel("alpha.0"), Some(0));
+        assert_eq!(channel("rc.3"), Some(2));
+        assert_eq!(channel("beta2"), Some(1));
+        assert_eq!(channel("pre0"), None);
+        assert!(channel("rc.0") > channel("alpha.1"));
+    }
+
+    #[test]
+    fn turns_branches_into_prerelease_identifiers() {
+        assert_eq!(
+            crate::branch_identifier("feature/fast-parser"),
+            "fast-parser"
+        );
+        assert_eq!(
+            crate::branch_identifier("fix/issue_12__again"),
+            "issue-12-again"
+        );
+        assert_eq!(crate::branch_identifier("main"), "main");
+    }
+
+    #[test]
+    fn finds_the_ticket_in_a_branch() {
+        let config = crate::Config::default();
+        assert_eq!(
+            crate::ticket("feature/JIRA-1234-fast-parser", &config).unwrap(),
+            "JIRA-1234"
+        );
+        assert!(crate::ticket("feature/fast-parser", &config).is_err());
+
+        let config = crate::Config {
+            ticket_regex: Some(r"issue-(\d+)".into()),
+            ..Default::default()
+        };
+        assert_eq!(crate::ticket("fix/issue-42", &config).unwrap(), "42");
+    }
+
+    #[test]
+    fn names_the_floating_aliases() {
+        let tag = |name: &str| Tag::try_from(name).unwrap();
+        let known = [tag("v1.4.2"), tag("v1.5.0"), tag("v2.0.0-rc0")];
+        let aliases = crate::Aliases {
+            floating: true,
+            stable: Some("stable"),
+        };
+
+        assert_eq!(
+            crate::alias_names(&tag("v1.5.1"), &known, &aliases),
+            vec!["v1", "v1.5", "stable"]
+        );
+        assert_eq!(
+            crate::alias_names(&tag("v1.4.3"), &known, &aliases),
+            vec!["v1.4"]
+        );
+        assert_eq!(
+            crate::alias_names(&tag("api@2.0.0"), &known, &aliases),
+            vec!["api@2", "api@2.0", "api@stable"]
+        );
+        assert!(crate::alias_names(&tag("v2.0.0-rc1"), &known, &aliases).is_empty());
+    }
+
+    #[test]
+    fn bumps_four_part_versions() {
+        let (v, revision) = crate::parse_four_part("1.2.3.4-rc0").unwrap();
+        assert_eq!(v, semver::Version::parse("1.2.3-rc0").unwrap());
+        assert_eq!(revision, 4);
+        assert!(crate::parse_four_part("1.2.3").is_err());
+
+        let tag = Tag {
+            revision: Some(revision),
+            v: semver::Version::new(1, 2, 3),
+            ..Tag::initial(None, None)
+        };
+        let bump = |flags: &[&str]| {
+            use bpaf::Parser;
+            let args = crate::version_args().to_options().run_inner(flags).unwrap();
+            let next = crate::four_part_tag(tag.clone(), &args);
+            (next.v.to_string(), next.revision)
+        };
+        assert_eq!(bump(&["--patch"]), ("1.2.3".to_string(), Some(5)));
+        assert_eq!(bump(&["--minor"]), ("1.2.4".to_string(), Some(0)));
+        assert_eq!(bump(&["--major"]), ("1.3.0".to_string(), Some(0)));
+        assert_eq!(bump(&["--pre"]), ("1.2.3-pre0".to_string(), Some(5)));
+
+        let newer = Tag {
+            revision: Some(9),
+            ..tag.clone()
+        };
+        assert!(newer > tag);
+    }
+
+    #[test]
+    fn keeps_suffixed_channels_apart() {
+        let suffixes = ["lts".to_string()];
+        let lts = Tag::try_from("v1.8.12+lts").unwrap();
+        let main = Tag::try_from("v1.9.0").unwrap();
+        let built = Tag::try_from("v1.9.0+ci.4").unwrap();
+
+        assert!(crate::in_stream(&lts, Some("lts"), &suffixes));
+        assert!(!crate::in_stream(&main, Some("lts"), &suffixes));
+        assert!(!crate::in_stream(&lts, None, &suffixes));
+        assert!(crate::in_stream(&main, None, &suffixes));
+        assert!(crate::in_stream(&built, None, &suffixes));
+    }
+
+    #[test]
+    fn finds_the_line_of_a_release_branch() {
+        use crate::ReleaseLine;
+
+        let line = ReleaseLine::from_branch("release/1.x").unwrap();
+        assert_eq!(line.to_string(), "1.x");
+        assert!(line.contains(&semver::Version::new(1, 9, 4)));
+        assert!(!line.contains(&semver::Version::new(2, 0, 0)));
+
+        let line = ReleaseLine::from_branch("release/v1.4.x").unwrap();
+        assert_eq!(line.to_string(), "1.4.x");
+        assert!(!line.contains(&semver::Version::new(1, 5, 0)));
+        assert_eq!(line.first(), semver::Version::new(1, 4, 0));
+
+        assert_eq!(ReleaseLine::from_branch("release/next"), None);
+        assert_eq!(ReleaseLine::from_branch("main"), None);
+    }
+
+    #[test]
+    fn previews_every_bump() {
+        let base = Tag::try_from("v1.4.2").unwrap();
+        let options = crate::bump_options(&base, &crate::VersionArgs::default(), 2025);
+
+        let rendered: Vec<String> = options
+            .iter()
+            .map(|(flag, tag)| format!("{flag} {tag}"))
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                "--major v2.0.0",
+                "--minor v1.5.0",
+                "--patch v1.4.3",
+                "--pre v1.4.3-pre0"
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_up_build_numbers() {
+        let latest = Tag {
+            v: semver::Version::new(1234, 0, 0),
+            ..Tag::initial(Some("api".into()), None)
+        };
+
+        let next = crate::build_number_tag(Some(&latest), latest.prefix.clone());
+        assert_eq!(next.v, semver::Version::new(1235, 0, 0));
+        assert_eq!(next.prefix.as_deref(), Some("api"));
+        assert_eq!(crate::build_number_tag(None, None).v.major, 1);
+    }
+
+    #[test]
+    fn orders_tags_by_epoch() {
+        let tag = |name: &str| Tag::try_from(name).unwrap();
+
+        assert_eq!(tag("v2!1.4.0").epoch, 2);
+        assert_eq!(tag("v2!1.4.0").to_string(), "v2!1.4.0");
+        assert_eq!(tag("api@1!0.1.0").to_string(), "api@1!0.1.0");
+        assert!(tag("v1!0.1.0") > tag("v9.0.0"));
+        assert!(tag("v2!1.4.0") > tag("v1!9.0.0"));
+        assert!(Tag::try_from("vx!1.0.0").is_err());
+
+        let next = crate::increment_tag(tag("v2!1.4.0"), &crate::VersionArgs::default());
+        assert_eq!(next.to_string(), "v2!1.4.1-pre0");
+    }
+
+    #[test]
+    fn finds_skipped_versions() {
+        let skipped = |latest: &str, next: &str| {
+            let latest = semver::Version::parse(latest).unwrap();
+            let next = semver::Version::parse(next).unwrap();
+            crate::skipped_versions(&latest, &next)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(skipped("1.2.3", "1.2.7"), ["1.2.4", "1.2.5", "1.2.6"]);
+        assert_eq!(skipped("1.2.3", "1.4.1"), ["1.3.0", "1.4.0"]);
+        assert_eq!(skipped("1.2.3", "3.0.0"), ["2.0.0"]);
+        assert_eq!(skipped("1.3.0-rc1", "1.3.1"), ["1.3.0"]);
+        assert!(skipped("1.2.3", "1.2.4-pre0").is_empty());
+        assert!(skipped("1.2.3", "1.3.0").is_empty());
+        assert!(skipped("1.2.3", "2.0.0").is_empty());
+        assert!(skipped("1.3.0-rc1", "1.3.0").is_empty());
+        assert!(skipped("1.2.3", "1.2.0").is_empty());
+    }
+
+    #[test]
+    fn enforces_the_minimum_version() {
+        let config = crate::Config {
+            min_version: Some(semver::Version::new(2, 0, 0)),
+            ..Default::default()
+        };
+        let tag = |name: &str| Tag::try_from(name).unwrap();
+
+        assert!(crate::check_min_version(&tag("v2.0.0"), &config).is_ok());
+        assert!(crate::check_min_version(&tag("v2.0.1-pre0"), &config).is_ok());
+        assert!(crate::check_min_version(&tag("v1.9.9"), &config).is_err());
+        assert!(crate::check_min_version(&tag("v2.0.0-rc0"), &config).is_err());
+    }
+
+    #[test]
+    fn summarises_the_road_to_1_0_0() {
+        let tags = ["v0.1.0", "v0.2.0", "v0.2.1", "v0.3.0-rc0"].map(|t| Tag::try_from(t).unwrap());
+
+        assert_eq!(
+            crate::graduation_summary(&tags, 1),
+            "3 releases from v0.1.0 to v0.2.1 with 1 breaking change along the way"
+        );
+        assert_eq!(
+            crate::graduation_summary(&tags[3..], 0),
+            "no releases yet with 0 breaking changes along the way"
+        );
+    }
+
+    #[test]
+    fn prefers_the_upstream_remote() {
+        assert_eq!(
+            crate::preferred_remote("origin\nupstream\n", "origin"),
+            "upstream"
+        );
+        assert_eq!(crate::preferred_remote("origin\n", "origin"), "origin");
+        assert_eq!(crate::preferred_remote("", "origin"), "origin");
+        assert_eq!(crate::preferred_remote("upstream-mirror\n", "fork"), "fork");
+    }
+
+    #[test]
+    fn recognises_bare_repositories() {
+        use crate::{repository_kind, Backend, RepoType};
+        let checkout = || -> bool { panic!("a checkout is never asked whether it is bare") };
+
+        assert_eq!(
+            repository_kind(Backend::Auto, false, false, || true),
+            Some((RepoType::Git, true))
+        );
+        assert_eq!(
+            repository_kind(Backend::Auto, false, true, checkout),
+            Some((RepoType::Git, false))
+        );
+        assert_eq!(repository_kind(Backend::Auto, false, false, || false), None);
+        // jj has no bare repositories
+        assert_eq!(repository_kind(Backend::Jj, false, false, || true), None);
+    }
+
+    #[test]
+    fn picks_the_backend() {
+        use crate::{check_backend, repository_kind, Backend, RepoType};
+        let never = || -> bool { panic!("not a bare repository") };
+
+        assert_eq!("auto".parse::<Backend>(), Ok(Backend::Auto));
+        assert_eq!("jj".parse::<Backend>(), Ok(Backend::Jj));
+        assert!("hg".parse::<Backend>().is_err());
+
+        // A colocated repository
+        assert_eq!(
+            repository_kind(Backend::Auto, true, true, never),
+            Some((RepoType::Jj, false))
+        );
+        assert_eq!(
+            repository_kind(Backend::Git, true, true, never),
+            Some((RepoType::Git, false))
+        );
+        assert_eq!(repository_kind(Backend::Git, true, false, || false), None);
+        assert_eq!(repository_kind(Backend::Jj, false, true, never), None);
+
+        assert!(check_backend(Backend::Git, true, true).is_ok());
+        assert!(check_backend(Backend::Jj, false, false).is_ok());
+        assert!(check_backend(Backend::Jj, true, false).is_err());
+        assert!(check_backend(Backend::Jj, false, true).is_err());
+    }
+
+    #[test]
+    fn finds_the_tag_to_bump_from() {
+        let tag = |name: &str| Tag::try_from(name).unwrap();
+        let tags = [tag("v1.3.0"), tag("v2.1.0"), tag("api@v1.0.0")];
+
+        let found = crate::find_from_tag("v1.3.0", None, &tags).unwrap();
+        assert_eq!(found.to_string(), "v1.3.0");
+        let found = crate::find_from_tag("v1.0.0", Some("api"), &tags).unwrap();
+        assert_eq!(found.to_string(), "api@v1.0.0");
+        assert!(crate::find_from_tag("v1.0.0", None, &tags).is_err());
+        assert!(crate::find_from_tag("v9.9.9", None, &tags).is_err());
+        assert!(crate::find_from_tag("latest", None, &tags).is_err());
+    }
+
+    #[test]
+    fn recognises_dated_nightly_tags() {
+        assert!(crate::is_dated_nightly("nightly-2025-03-11"));
+        assert!(!crate::is_dated_nightly("nightly"));
+        assert!(!crate::is_dated_nightly("nightly-build"));
+        assert!(Tag::try_from("nightly-2025-03-11").is_err());
     }
 
     #[test]
@@ -605,7 +5303,7 @@ mod tests {
         let before = Tag::try_from("v0.1.0").unwrap();
         let after = increment_tag(
             before,
-            &crate::Args {
+            &crate::VersionArgs {
                 major: true,
                 minor: false,
                 patch: false,
@@ -622,7 +5320,7 @@ mod tests {
         let before = Tag::try_from("v0.1.1").unwrap();
         let after = increment_tag(
             before,
-            &crate::Args {
+            &crate::VersionArgs {
                 major: false,
                 minor: true,
                 patch: false,
@@ -639,7 +5337,7 @@ mod tests {
         let before = Tag::try_from("v0.1.1").unwrap();
         let after = increment_tag(
             before,
-            &crate::Args {
+            &crate::VersionArgs {
                 major: false,
                 minor: false,
                 patch: true,
@@ -656,7 +5354,7 @@ mod tests {
         let before = Tag::try_from("v0.1.1-pre5").unwrap();
         let after = increment_tag(
             before,
-            &crate::Args {
+            &crate::VersionArgs {
                 major: false,
                 minor: false,
                 patch: false,
@@ -673,7 +5371,7 @@ mod tests {
         let before = Tag::try_from("v0.1.1-pre5").unwrap();
         let after = increment_tag(
             before,
-            &crate::Args {
+            &crate::VersionArgs {
                 major: false,
                 minor: false,
                 patch: true,
@@ -690,7 +5388,7 @@ mod tests {
         let before = Tag::try_from("v0.1.1").unwrap();
         let after = increment_tag(
             before,
-            &crate::Args {
+            &crate::VersionArgs {
                 major: false,
                 minor: false,
                 patch: false,
@@ -702,12 +5400,54 @@ mod tests {
         assert_eq!(after, Tag::try_from("v0.1.2-pre0").unwrap());
     }
 
+    #[test]
+    fn uses_the_given_prerelease_identifier() {
+        let params = crate::VersionArgs {
+            pre: true,
+            pre_id: Some("rc".into()),
+            ..Default::default()
+        };
+
+        let after = increment_tag(Tag::try_from("v0.1.1").unwrap(), &params);
+        assert_eq!(after, Tag::try_from("v0.1.2-rc0").unwrap());
+
+        let after = increment_tag(Tag::try_from("v0.1.2-rc0").unwrap(), &params);
+        assert_eq!(after, Tag::try_from("v0.1.2-rc1").unwrap());
+
+        let after = increment_tag(Tag::try_from("v0.1.2-rc.3").unwrap(), &params);
+        assert_eq!(after, Tag::try_from("v0.1.2-rc.4").unwrap());
+
+        let params = crate::VersionArgs {
+            pre: true,
+            pre_id: Some("beta.".into()),
+            ..Default::default()
+        };
+        let after = increment_tag(Tag::try_from("v0.1.1").unwrap(), &params);
+        assert_eq!(after, Tag::try_from("v0.1.2-beta.0").unwrap());
+    }
+
+    #[test]
+    fn pads_the_prerelease_counter() {
+        let params = crate::VersionArgs {
+            pre: true,
+            pre_padding: Some(3),
+            ..Default::default()
+        };
+
+        let after = increment_tag(Tag::try_from("v0.1.1").unwrap(), &params);
+        assert_eq!(after.to_string(), "v0.1.2-pre000");
+        let after = increment_tag(Tag::try_from("v0.1.2-pre009").unwrap(), &params);
+        assert_eq!(after.to_string(), "v0.1.2-pre010");
+        let after = increment_tag(Tag::try_from("v0.1.2-pre.9").unwrap(), &params);
+        assert_eq!(after.to_string(), "v0.1.2-pre.10");
+    }
+
     #[test]
     fn can_choose_to_bump_any_other_field_with_pretag() {
         let before = Tag::try_from("v0.1.1").unwrap();
         let after = increment_tag(
             before,
-            &crate::Args {
+            &crate::VersionArgs {
                 major: false,
                 minor: true,
                 patch: false,
@@ -721,7 +5461,7 @@ mod tests {
         let before = Tag::try_from("v0.1.1").unwrap();
         let after = increment_tag(
             before,
-            &crate::Args {
+            &crate::VersionArgs {
                 major: true,
                 minor: false,
                 patch: false,
@@ -0,0 +1,243 @@
+//! Replaces the running nutag binary with the latest release published on GitHub.
+
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context};
+use log::{debug, info};
+use nanoserde::DeJson;
+use ureq::Agent;
+
+use crate::digest::sha256_hex;
+use crate::forge;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/felipesere/nutag/releases/latest";
+
+/// Release tarballs are a few MB, this leaves plenty of headroom
+const MAX_DOWNLOAD_SIZE: u64 = 100 * 1024 * 1024;
+
+#[derive(Debug, DeJson)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, DeJson)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// The name of the release tarball for the platform nutag is running on,
+/// matching the `asset_name`s of the release workflow
+fn asset_name() -> Result<String, anyhow::Error> {
+    let os = match std::env::consts::OS {
+        os @ ("linux" | "macos") => os,
+        other => bail!("There are no nutag releases for {other}"),
+    };
+    let arch = match std::env::consts::ARCH {
+        arch @ ("x86_64" | "aarch64") => arch,
+        other => bail!("There are no nutag releases for {other}"),
+    };
+    Ok(format!("nutag-{os}-{arch}.tar.gz"))
+}
+
+pub fn run(current_version: &str, check_only: bool) -> Result<(), anyhow::Error> {
+    let release: Release = get_json(RELEASES_URL)?;
+    let latest = release
+        .tag_name
+        .strip_prefix('v')
+        .unwrap_or(&release.tag_name);
+
+    let up_to_date = match (
+        semver::Version::parse(current_version),
+        semver::Version::parse(latest),
+    ) {
+        (Ok(current), Ok(latest)) => current >= latest,
+        // Local builds don't know their version, so always offer the release
+        _ => false,
+    };
+
+    if up_to_date {
+        println!("nutag {current_version} is up to date");
+        return Ok(());
+    }
+
+    if check_only {
+        println!("nutag {latest} is available (currently {current_version})");
+        return Ok(());
+    }
+
+    let asset_name = asset_name()?;
+    let asset = find_asset(&release, &asset_name)?;
+    let expected = expected_checksum(&release, &asset_name)?;
+
+    info!("Downloading {}", asset.browser_download_url);
+    let tarball = download(&asset.browser_download_url)?;
+
+    let actual = sha256_hex(&tarball);
+    if actual != expected {
+        bail!("Checksum mismatch for {asset_name}: expected {expected}, got {actual}");
+    }
+    debug!("Checksum {actual} matches");
+
+    let current_exe = std::env::current_exe().context("locating the nutag binary")?;
+    install(&tarball, &current_exe)?;
+
+    println!("Updated nutag from {current_version} to {latest}");
+    Ok(())
+}
+
+fn find_asset<'a>(release: &'a Release, name: &str) -> Result<&'a Asset, anyhow::Error> {
+    release
+        .assets
+        .iter()
+        .find(|asset| asset.name == name)
+        .with_context(|| format!("release {} has no {name}", release.tag_name))
+}
+
+/// Looks for either a `<asset>.sha256` file or a combined checksums file listing the asset
+fn expected_checksum(release: &Release, asset_name: &str) -> Result<String, anyhow::Error> {
+    let dedicated = format!("{asset_name}.sha256");
+    let candidates = release.assets.iter().filter(|asset| {
+        asset.name == dedicated
+            || asset.name.eq_ignore_ascii_case("SHA256SUMS")
+            || asset.name.to_lowercase().contains("checksums")
+    });
+
+    for asset in candidates {
+        let raw = download(&asset.browser_download_url)?;
+        let listing = String::from_utf8_lossy(&raw);
+        if let Some(checksum) = find_checksum(&listing, asset_name) {
+            return Ok(checksum);
+        }
+    }
+
+    bail!(
+        "release {} has no checksum for {asset_name}, refusing to install it",
+        release.tag_name
+    )
+}
+
+/// Finds the checksum in `sha256sum`-style output. A listing with a single bare hash is
+/// assumed to belong to the asset.
+fn find_checksum(listing: &str, asset_name: &str) -> Option<String> {
+    let is_hash = |s: &str| s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit());
+
+    let entries: Vec<&str> = listing
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+
+    if let [single] = entries.as_slice() {
+        if is_hash(single) {
+            return Some(single.to_lowercase());
+        }
+    }
+
+    entries.iter().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next()?;
+        let file = parts.next()?.trim_start_matches('*');
+        (is_hash(hash) && file == asset_name).then(|| hash.to_lowercase())
+    })
+}
+
+/// Unpacks the tarball next to the current binary and renames it over the top,
+/// so the swap is atomic and never leaves a half-written executable behind.
+fn install(tarball: &[u8], current_exe: &Path) -> Result<(), anyhow::Error> {
+    let dir = current_exe
+        .parent()
+        .context("the nutag binary has no parent directory")?;
+    let staging = dir.join(format!(".nutag-update-{}", std::process::id()));
+    std::fs::create_dir_all(&staging)?;
+
+    let result = (|| {
+        let archive = staging.join("nutag.tar.gz");
+        std::fs::write(&archive, tarball)?;
+
+        let output = Command::new("tar")
+            .arg("-xzf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(&staging)
+            .output()
+            .context("running tar")?;
+        if !output.status.success() {
+            bail!(
+                "tar failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let binary = staging.join("nutag");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755))?;
+        }
+
+        std::fs::rename(&binary, current_exe)
+            .with_context(|| format!("replacing {}", current_exe.display()))?;
+        Ok(())
+    })();
+
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+fn request(url: &str) -> ureq::RequestBuilder<ureq::typestate::WithoutBody> {
+    static AGENT: OnceLock<Agent> = OnceLock::new();
+    let agent = AGENT.get_or_init(|| forge::http_config().build().new_agent());
+    let request = agent.get(url).header("User-Agent", "nutag");
+    // Not required, but raises the rate limit
+    match std::env::var("GITHUB_TOKEN") {
+        Ok(token) => request.header("Authorization", &format!("Bearer {token}")),
+        Err(_) => request,
+    }
+}
+
+fn get_json<T: DeJson>(url: &str) -> Result<T, anyhow::Error> {
+    let mut response = request(url)
+        .header("Accept", "application/vnd.github+json")
+        .call()?;
+    let body = response.body_mut().read_to_string()?;
+    DeJson::deserialize_json(&body).with_context(|| format!("parsing response from {url}"))
+}
+
+fn download(url: &str) -> Result<Vec<u8>, anyhow::Error> {
+    let mut response = request(url).call()?;
+    let bytes = response
+        .body_mut()
+        .with_config()
+        .limit(MAX_DOWNLOAD_SIZE)
+        .read_to_vec()?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_checksum;
+
+    const HASH: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    #[test]
+    fn finds_the_checksum_of_the_asset() {
+        let listing = format!(
+            "{}  nutag-linux-aarch64.tar.gz\n{HASH}  nutag-linux-x86_64.tar.gz\n",
+            "0".repeat(64)
+        );
+
+        assert_eq!(
+            find_checksum(&listing, "nutag-linux-x86_64.tar.gz").as_deref(),
+            Some(HASH)
+        );
+        assert_eq!(find_checksum(&listing, "nutag-macos-x86_64.tar.gz"), None);
+        assert_eq!(
+            find_checksum(HASH, "nutag-linux-x86_64.tar.gz").as_deref(),
+            Some(HASH)
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Tag names in a scheme of the project's own, e.g. `myapp_v1_2_3`, described by a regex
+//! to parse existing tags and a template to render new ones.
+
+use anyhow::{bail, Context};
+use regex_lite::Regex;
+use semver::{Prerelease, Version};
+
+use crate::template;
+
+#[derive(Debug)]
+pub struct TagFormat {
+    regex: Regex,
+    template: String,
+}
+
+impl TagFormat {
+    /// The regex needs `major`, `minor` and `patch` groups, `pre` and `prefix` are optional.
+    /// The template can use the same names as variables and needs `{pre}`, so prereleases
+    /// don't get the name of their release.
+    pub fn new(regex: &str, template: &str) -> Result<Self, anyhow::Error> {
+        let regex = Regex::new(regex).context("`tag_regex` is not a valid regex")?;
+        for group in ["major", "minor", "patch"] {
+            if !regex.capture_names().any(|name| name == Some(group)) {
+                bail!("`tag_regex` has no (?P<{group}>...) group");
+            }
+        }
+
+        if !template.contains("{pre}") {
+            bail!("`tag_template` has no {{pre}}, prereleases would get the name of their release");
+        }
+
+        let format = Self {
+            regex,
+            template: template.to_string(),
+        };
+        // Catches unknown variables now, so rendering can't fail when printing a tag
+        format
+            .try_render(None, &Version::new(1, 2, 3))
+            .context("`tag_template`")?;
+        Ok(format)
+    }
+
+    /// The prefix and version of a tag name, if the regex matches it
+    pub fn parse(&self, name: &str) -> Option<(Option<String>, Version)> {
+        let captures = self.regex.captures(name)?;
+        let number = |group: &str| captures.name(group)?.as_str().parse::<u64>().ok();
+
+        let mut version = Version::new(number("major")?, number("minor")?, number("patch")?);
+        if let Some(pre) = captures.name("pre").filter(|pre| !pre.is_empty()) {
+            version.pre = Prerelease::new(pre.as_str()).ok()?;
+        }
+        let prefix = captures
+            .name("prefix")
+            .map(|prefix| prefix.as_str().to_string());
+        Some((prefix, version))
+    }
+
+    pub fn render(&self, prefix: Option<&str>, version: &Version) -> String {
+        self.try_render(prefix, version)
+            .expect("the template was checked in TagFormat::new")
+    }
+
+    fn try_render(&self, prefix: Option<&str>, version: &Version) -> Result<String, anyhow::Error> {
+        template::render(
+            &self.template,
+            &[
+                ("prefix", prefix.unwrap_or_default().to_string()),
+                ("major", version.major.to_string()),
+                ("minor", version.minor.to_string()),
+                ("patch", version.patch.to_string()),
+                ("pre", version.pre.to_string()),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::Version;
+
+    use super::TagFormat;
+
+    #[test]
+    fn parses_and_renders_custom_tags() {
+        let format = TagFormat::new(
+            r"^myapp_v(?P<major>\d+)_(?P<minor>\d+)_(?P<patch>\d+)(?P<pre>[a-z][0-9a-z.]*)?$",
+            "myapp_v{major}_{minor}_{patch}{pre}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            format.parse("myapp_v1_2_3"),
+            Some((None, Version::new(1, 2, 3)))
+        );
+        assert_eq!(
+            format.parse("myapp_v1_2_3rc1").unwrap().1,
+            Version::parse("1.2.3-rc1").unwrap()
+        );
+        assert_eq!(format.parse("v1.2.3"), None);
+        assert_eq!(format.render(None, &Version::new(2, 0, 0)), "myapp_v2_0_0");
+        assert_eq!(
+            format.render(None, &Version::parse("2.0.0-rc.1").unwrap()),
+            "myapp_v2_0_0rc.1"
+        );
+
+        assert!(TagFormat::new(r"^v(?P<major>\d+)$", "v{major}").is_err());
+        assert!(TagFormat::new(
+            r"^(?P<major>\d+)_(?P<minor>\d+)_(?P<patch>\d+)$",
+            "{major}_{minor}_{build}"
+        )
+        .is_err());
+        // Prereleases would get the name of their release
+        assert!(TagFormat::new(
+            r"^(?P<major>\d+)_(?P<minor>\d+)_(?P<patch>\d+)(-(?P<pre>.+))?$",
+            "{major}_{minor}_{patch}"
+        )
+        .is_err());
+    }
+}
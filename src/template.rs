@@ -0,0 +1,62 @@
+//! Tag messages with `{variable}` placeholders, e.g. `Release {version} ({commit_short})`.
+
+use anyhow::bail;
+
+/// Replaces every `{name}` with the matching value, `{{` and `}}` are literal braces.
+/// Unknown variables are an error rather than being left in the message.
+pub fn render(template: &str, vars: &[(&str, String)]) -> Result<String, anyhow::Error> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(idx) = rest.find(['{', '}']) {
+        out.push_str(&rest[..idx]);
+        let tail = &rest[idx..];
+
+        if let Some(after) = tail.strip_prefix("{{") {
+            out.push('{');
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix("}}") {
+            out.push('}');
+            rest = after;
+        } else if tail.starts_with('}') {
+            bail!("unmatched `}}` in message template");
+        } else {
+            let Some(end) = tail.find('}') else {
+                bail!("unclosed `{{` in message template");
+            };
+            let name = &tail[1..end];
+            let Some((_, value)) = vars.iter().find(|(var, _)| *var == name) else {
+                let known: Vec<&str> = vars.iter().map(|(var, _)| *var).collect();
+                bail!(
+                    "unknown variable {{{name}}} in message template, expected one of {}",
+                    known.join(", ")
+                );
+            };
+            out.push_str(value);
+            rest = &tail[end + 1..];
+        }
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+
+    #[test]
+    fn replaces_variables() {
+        let vars = [
+            ("version", "1.2.3".to_string()),
+            ("commit_short", "abc1234".to_string()),
+        ];
+
+        assert_eq!(
+            render("Release {version} ({commit_short}) {{literal}}", &vars).unwrap(),
+            "Release 1.2.3 (abc1234) {literal}"
+        );
+        assert!(render("Release {nope}", &vars).is_err());
+        assert!(render("Release {version", &vars).is_err());
+    }
+}